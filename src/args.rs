@@ -1,5 +1,14 @@
-use clap::Parser;
+use chrono::NaiveTime;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, Parser, Subcommand, ValueEnum};
 use crate::ascii;
+use crate::blocking::BlockProfile;
+use crate::course::{CourseQuery, Credits};
+use crate::ics::IcsScope;
+use crate::plan::Method;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "course-sniper")]
@@ -7,14 +16,379 @@ use crate::ascii;
 #[command(about = ascii::BANNER, long_about = None)]
 pub struct SniperArgs {
     /// Attaches to the headless browser and displays it
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub attach: bool,
 
+    /// Connects to an already running Chrome (started with --remote-debugging-port) instead of launching one
+    #[arg(short, long, value_name = "WS_URL", conflicts_with = "attach", global = true)]
+    pub connect: Option<String>,
+
     /// Number of snipers that will run (not currently implemented)
-    #[arg(short, long, value_name = "NUMBER", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..20))]
+    #[arg(short, long, value_name = "NUMBER", default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..20), global = true)]
     pub snipers: u8,
 
-    /// Enables debug mode which screenshots the page on error
-    #[arg(short, long)]
+    /// Enables debug mode which records network traffic and saves a debug bundle on error
+    #[arg(short, long, global = true)]
     pub debug: bool,
+
+    /// Fails on a page that isn't recognized instead of pausing for it to be fixed by hand
+    #[arg(long, global = true)]
+    pub no_pause: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Restores a session saved with the login command, skipping sign in while it lasts
+    #[arg(long, value_name = "FILE", global = true)]
+    pub session: Option<PathBuf>,
+
+    /// Shopping cart to enter without prompting, matched against the cart names ignoring case or as a regex
+    #[arg(long, value_name = "TERM", visible_alias = "cart", global = true)]
+    pub term: Option<String>,
+
+    /// Course to target instead of picking from a list, by class number or as "SUBJ NUM-SEC", can be repeated
+    #[arg(id = "course", long = "course", value_name = "COURSE", global = true)]
+    pub courses: Vec<CourseQuery>,
+
+    /// Answers the prompts from a plan saved with --save-plan
+    #[arg(long, value_name = "FILE", global = true)]
+    pub plan: Option<PathBuf>,
+
+    /// Saves the cart, courses, action, method and time chosen to a plan file
+    #[arg(long, value_name = "FILE", global = true)]
+    pub save_plan: Option<PathBuf>,
+
+    /// Resources to block during the enrollment reload (the result icons are always kept)
+    #[arg(long, value_name = "PROFILE", value_enum, default_value_t = BlockProfile::None, global = true)]
+    pub block_resources: BlockProfile,
+
+    /// Writes the schedule to an iCalendar file
+    #[arg(long, value_name = "FILE", global = true)]
+    pub export_ics: Option<PathBuf>,
+
+    /// Courses to put in the calendar: the whole cart or only successful enrollments
+    #[arg(long, value_name = "SCOPE", value_enum, default_value_t = IcsScope::Enrolled, requires = "export_ics", global = true)]
+    pub ics_scope: IcsScope,
+
+    #[command(flatten)]
+    pub browser: BrowserArgs,
+
+    #[command(flatten)]
+    pub credits: CreditLimits,
+
+    #[command(flatten)]
+    pub timeouts: Timeouts,
+}
+
+/// What to do, asked for interactively when no command is given.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Signs in, approving Duo if asked, and saves the session for --session
+    Login(LoginArgs),
+    /// Lists the courses in the shopping cart
+    Cart,
+    /// Validates the selected courses
+    Validate,
+    /// Enrolls in the selected courses at the registration time
+    Enroll(EnrollArgs),
+    /// Reloads the cart until a selected course has open seats, then enrolls in it
+    Watch(WatchArgs),
+    /// Compares cart reload times with and without resource blocking
+    Bench(BenchArgs),
+    /// Walks through the site without enrolling and checks every page selector still matches
+    Doctor,
+}
+
+#[derive(Args, Debug)]
+pub struct LoginArgs {
+    /// Where to save the session
+    #[arg(long, value_name = "FILE", default_value = "course-sniper-session.json")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct EnrollArgs {
+    /// How to send the enrollment (asked for when not given)
+    #[arg(long, value_enum)]
+    pub method: Option<Method>,
+
+    /// Local registration time, e.g. 07:00 or 7:00AM (asked for when not given)
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub at: Option<NaiveTime>,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Seconds between cart reloads
+    #[arg(long, value_name = "SECS", default_value = "60", value_parser = parse_secs)]
+    pub interval: Duration,
+
+    /// How to send each enrollment
+    #[arg(long, value_enum, default_value_t = Method::Legacy)]
+    pub method: Method,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Reloads timed per blocking mode
+    #[arg(long, value_name = "NUMBER", default_value_t = 5)]
+    pub rounds: usize,
+
+    /// Blocking to compare against (defaults to --block-resources, or asks)
+    #[arg(long, value_enum)]
+    pub profile: Option<BlockProfile>,
+}
+
+/// What to do when a selection is over or under the credit limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CreditCheck {
+    /// Print a warning and go on
+    Warn,
+    /// Reject the selection
+    Stop,
+}
+
+/// Credit load limits checked against the selected courses.
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Credit limits")]
+pub struct CreditLimits {
+    /// Most credits PeopleSoft allows without an overload
+    #[arg(long, value_name = "CREDITS", global = true)]
+    pub max_credits: Option<f32>,
+
+    /// Fewest credits to enroll in, e.g. for full-time status
+    #[arg(long, value_name = "CREDITS", global = true)]
+    pub min_credits: Option<f32>,
+
+    /// Whether a selection outside the limits is rejected or only warned about
+    #[arg(long, value_name = "ACTION", value_enum, default_value_t = CreditCheck::Stop, global = true)]
+    pub credit_check: CreditCheck,
+}
+
+impl CreditLimits {
+    /// Describes how the total breaks a limit. Variable credits only break one when even the
+    /// most favorable choice within their range would.
+    pub fn violation(&self, total: Credits) -> Option<String> {
+        match (self.max_credits, self.min_credits) {
+            (Some(max), _) if total.min > max => Some(format!(
+                "{total} credits selected, over the maximum of {max}"
+            )),
+            (_, Some(min)) if total.max < min => Some(format!(
+                "{total} credits selected, under the minimum of {min}"
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Browser")]
+pub struct BrowserArgs {
+    /// Path to the Chrome or Chromium executable (detected automatically by default)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub chrome_path: Option<PathBuf>,
+
+    /// Persistent browser profile directory, keeps cookies such as Duo's "trust this browser" between runs
+    #[arg(long, value_name = "DIR", global = true)]
+    pub user_data_dir: Option<PathBuf>,
+
+    /// Browser window size
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size, global = true)]
+    pub window_size: Option<(u32, u32)>,
+
+    /// Extra argument passed to Chrome, can be repeated
+    #[arg(long = "chrome-arg", value_name = "ARG", allow_hyphen_values = true, global = true)]
+    pub chrome_args: Vec<String>,
+
+    /// HTTP or SOCKS proxy for all browser traffic (e.g. socks5://127.0.0.1:1080)
+    #[arg(long, value_name = "URL", global = true)]
+    pub proxy: Option<String>,
+
+    /// Seconds to wait for a response to each browser command
+    #[arg(long, value_name = "SECS", global = true)]
+    pub request_timeout: Option<u64>,
+}
+
+/// How long each phase of a run waits for the page before failing.
+#[derive(Args, Debug, Clone)]
+#[command(next_help_heading = "Timeouts (seconds)")]
+pub struct Timeouts {
+    /// Submitting credentials until the login is accepted or rejected
+    #[arg(long = "login-timeout", value_name = "SECS", default_value = "120", value_parser = parse_secs, global = true)]
+    pub login: Duration,
+
+    /// Waiting for the Duo push to be approved
+    #[arg(long = "duo-timeout", value_name = "SECS", default_value = "120", value_parser = parse_secs, global = true)]
+    pub duo: Duration,
+
+    /// Finding the shopping cart or the cart picker
+    #[arg(long = "cart-timeout", value_name = "SECS", default_value = "120", value_parser = parse_secs, global = true)]
+    pub cart: Duration,
+
+    /// Loading the courses in the cart
+    #[arg(long = "courses-timeout", value_name = "SECS", default_value = "120", value_parser = parse_secs, global = true)]
+    pub courses: Duration,
+
+    /// Finding the course checkboxes to select
+    #[arg(long = "selection-timeout", value_name = "SECS", default_value = "30", value_parser = parse_secs, global = true)]
+    pub selection: Duration,
+
+    /// Finding the enroll or validate button
+    #[arg(long = "enroll-timeout", value_name = "SECS", default_value = "30", value_parser = parse_secs, global = true)]
+    pub enroll: Duration,
+
+    /// Finding the enrollment confirm button
+    #[arg(long = "confirm-timeout", value_name = "SECS", default_value = "30", value_parser = parse_secs, global = true)]
+    pub confirm: Duration,
+
+    /// Waiting for enrollment or validation results
+    #[arg(long = "results-timeout", value_name = "SECS", default_value = "30", value_parser = parse_secs, global = true)]
+    pub results: Duration,
+
+    /// Seconds of each phase set by a flag or a plan, by arg id, for `--save-plan`.
+    #[arg(skip)]
+    pub chosen: BTreeMap<String, u64>,
+}
+
+impl Timeouts {
+    // Each phase's timeout by arg id.
+    fn phases(&mut self) -> [(&'static str, &mut Duration); 8] {
+        [
+            ("login", &mut self.login),
+            ("duo", &mut self.duo),
+            ("cart", &mut self.cart),
+            ("courses", &mut self.courses),
+            ("selection", &mut self.selection),
+            ("enroll", &mut self.enroll),
+            ("confirm", &mut self.confirm),
+            ("results", &mut self.results),
+        ]
+    }
+
+    /// Uses a plan's timeouts for the phases without a flag, noting every timeout set either
+    /// way so a plan saved from this run keeps them.
+    pub fn apply(&mut self, matches: &ArgMatches, saved: &BTreeMap<String, u64>) -> Result<(), String> {
+        let mut chosen = BTreeMap::new();
+        for (phase, timeout) in self.phases() {
+            if matches.value_source(phase) == Some(ValueSource::CommandLine) {
+                chosen.insert(phase.to_string(), timeout.as_secs());
+            } else if let Some(&secs) = saved.get(phase) {
+                *timeout = Duration::from_secs(secs);
+                chosen.insert(phase.to_string(), secs);
+            }
+        }
+        if let Some(phase) = saved.keys().find(|phase| !chosen.contains_key(*phase)) {
+            return Err(format!("Unknown timeout `{phase}` in plan"));
+        }
+        self.chosen = chosen;
+        Ok(())
+    }
+}
+
+fn parse_secs(secs: &str) -> Result<Duration, String> {
+    secs.parse()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("expected a number of seconds, got `{secs}`"))
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    let compact = time.trim().replace(' ', "").to_uppercase();
+    NaiveTime::parse_from_str(&compact, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(&compact, "%I:%M%p"))
+        .map_err(|_| format!("expected a time like 07:00 or 7:00AM, got `{time}`"))
+}
+
+fn parse_window_size(size: &str) -> Result<(u32, u32), String> {
+    size.split_once(['x', 'X', ','])
+        .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+        .ok_or(format!("expected WIDTHxHEIGHT, got `{size}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    fn limits(max_credits: Option<f32>, min_credits: Option<f32>) -> CreditLimits {
+        CreditLimits {
+            max_credits,
+            min_credits,
+            credit_check: CreditCheck::Stop,
+        }
+    }
+
+    fn credits(min: f32, max: f32) -> Credits {
+        Credits { min, max }
+    }
+
+    fn timeouts(args: &[&str], saved: &[(&str, u64)]) -> Timeouts {
+        let matches = SniperArgs::command().get_matches_from(args);
+        let mut args = SniperArgs::from_arg_matches(&matches).unwrap();
+        let saved = saved
+            .iter()
+            .map(|(phase, secs)| (phase.to_string(), *secs))
+            .collect();
+        args.timeouts.apply(&matches, &saved).unwrap();
+        args.timeouts
+    }
+
+    #[test]
+    fn plan_timeouts_fill_phases_without_flags() {
+        let timeouts = timeouts(
+            &["course-sniper", "validate", "--login-timeout", "5"],
+            &[("login", 60), ("duo", 300)],
+        );
+        assert_eq!(timeouts.login, Duration::from_secs(5));
+        assert_eq!(timeouts.duo, Duration::from_secs(300));
+        assert_eq!(timeouts.cart, Duration::from_secs(120));
+        let chosen: Vec<(&str, u64)> =
+            timeouts.chosen.iter().map(|(phase, secs)| (phase.as_str(), *secs)).collect();
+        assert_eq!(chosen, vec![("duo", 300), ("login", 5)]);
+    }
+
+    #[test]
+    fn unknown_plan_timeout_is_rejected() {
+        let matches = SniperArgs::command().get_matches_from(["course-sniper"]);
+        let mut args = SniperArgs::from_arg_matches(&matches).unwrap();
+        let saved = BTreeMap::from([("reload".to_string(), 5)]);
+        assert!(args.timeouts.apply(&matches, &saved).is_err());
+    }
+
+    #[test]
+    fn no_limits_never_violated() {
+        assert_eq!(limits(None, None).violation(credits(0.0, 40.0)), None);
+    }
+
+    #[test]
+    fn over_maximum() {
+        let limits = limits(Some(18.0), None);
+        assert_eq!(limits.violation(credits(18.0, 18.0)), None);
+        assert_eq!(
+            limits.violation(credits(19.0, 19.0)).as_deref(),
+            Some("19 credits selected, over the maximum of 18")
+        );
+    }
+
+    #[test]
+    fn range_only_over_maximum_at_its_top_is_allowed() {
+        // taking the variable course for fewer credits keeps the load within the limit
+        assert_eq!(limits(Some(18.0), None).violation(credits(16.0, 20.0)), None);
+        assert!(limits(Some(18.0), None).violation(credits(19.0, 22.0)).is_some());
+    }
+
+    #[test]
+    fn under_minimum() {
+        let limits = limits(None, Some(12.0));
+        assert_eq!(limits.violation(credits(12.0, 12.0)), None);
+        assert_eq!(
+            limits.violation(credits(9.0, 9.0)).as_deref(),
+            Some("9 credits selected, under the minimum of 12")
+        );
+    }
+
+    #[test]
+    fn range_only_under_minimum_at_its_bottom_is_allowed() {
+        assert_eq!(limits(None, Some(12.0)).violation(credits(10.0, 13.0)), None);
+        assert!(limits(None, Some(12.0)).violation(credits(8.0, 11.0)).is_some());
+    }
 }
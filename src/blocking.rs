@@ -0,0 +1,221 @@
+use crate::elements::{EmoryPageElements, ToTable};
+use crate::wait;
+use async_std::task::JoinHandle;
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, DisableParams, EnableParams, EventRequestPaused, FailRequestParams,
+    RequestPattern,
+};
+use chromiumoxide::cdp::browser_protocol::network::{ErrorReason, ResourceType};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use clap::ValueEnum;
+use comfy_table::{Attribute, Cell, CellAlignment, Table};
+use futures::StreamExt;
+use serde::Serialize;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Which resources to drop during the enrollment reload.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockProfile {
+    /// Load everything
+    #[default]
+    None,
+    /// Block images, fonts and media
+    Media,
+    /// Also block stylesheets
+    All,
+}
+
+impl fmt::Display for BlockProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockProfile::None => write!(f, "none"),
+            BlockProfile::Media => write!(f, "media"),
+            BlockProfile::All => write!(f, "all"),
+        }
+    }
+}
+
+impl BlockProfile {
+    fn resource_types(&self) -> Vec<ResourceType> {
+        match self {
+            BlockProfile::None => vec![],
+            BlockProfile::Media => {
+                vec![ResourceType::Image, ResourceType::Font, ResourceType::Media]
+            }
+            BlockProfile::All => vec![
+                ResourceType::Image,
+                ResourceType::Font,
+                ResourceType::Media,
+                ResourceType::Stylesheet,
+            ],
+        }
+    }
+}
+
+/// Fails matching requests through CDP `Fetch` interception while it's alive. Dropping it
+/// without `disable` (e.g. on Ctrl-C) still turns interception off, in the background.
+pub struct ResourceBlocker {
+    page: Page,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ResourceBlocker {
+    /// Starts blocking the profile's resources, except urls containing one of the `keep` paths.
+    pub async fn enable(
+        page: &Page,
+        profile: BlockProfile,
+        keep: Vec<&'static str>,
+    ) -> Result<Self, CdpError> {
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        let patterns = profile
+            .resource_types()
+            .into_iter()
+            .map(|resource_type| {
+                RequestPattern::builder()
+                    .resource_type(resource_type)
+                    .build()
+            })
+            .collect::<Vec<RequestPattern>>();
+        page.execute(EnableParams::builder().patterns(patterns).build())
+            .await?;
+
+        let task_page = page.clone();
+        let task = async_std::task::spawn(async move {
+            while let Some(event) = paused.next().await {
+                let request_id = event.request_id.clone();
+                // paused requests hang until answered, so a failed reply is all we can do
+                let _ = if keep.iter().any(|path| event.request.url.contains(path)) {
+                    task_page
+                        .execute(ContinueRequestParams::new(request_id))
+                        .await
+                        .map(|_| ())
+                } else {
+                    task_page
+                        .execute(FailRequestParams::new(
+                            request_id,
+                            ErrorReason::BlockedByClient,
+                        ))
+                        .await
+                        .map(|_| ())
+                };
+            }
+        });
+
+        Ok(Self {
+            page: page.clone(),
+            task: Some(task),
+        })
+    }
+
+    pub async fn disable(mut self) -> Result<(), CdpError> {
+        self.page.execute(DisableParams::default()).await?;
+        if let Some(task) = self.task.take() {
+            task.cancel().await;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ResourceBlocker {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            let page = self.page.clone();
+            async_std::task::spawn(async move {
+                let _ = page.execute(DisableParams::default()).await;
+                task.cancel().await;
+            });
+        }
+    }
+}
+
+/// Reloads the cart and times how long until the course checkboxes are ready.
+pub async fn timed_reload(
+    page: &Page,
+    elements: &EmoryPageElements,
+    profile: BlockProfile,
+    timeout: Duration,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let blocker = match profile {
+        BlockProfile::None => None,
+        profile => Some(ResourceBlocker::enable(page, profile, elements.status_icons()).await?),
+    };
+    let start = Instant::now();
+    page.reload().await?.wait_for_navigation().await?;
+    wait::for_selector(page, &elements.checkboxes, timeout).await?;
+    let elapsed = start.elapsed();
+    if let Some(blocker) = blocker {
+        blocker.disable().await?;
+    }
+    Ok(elapsed)
+}
+
+/// Reload-to-ready times with and without blocking, taken alternately.
+pub struct ReloadComparison {
+    pub profile: BlockProfile,
+    pub unblocked: Vec<Duration>,
+    pub blocked: Vec<Duration>,
+}
+
+/// Alternates plain and blocked reloads of the cart so both see the same server conditions.
+pub async fn compare_reload(
+    page: &Page,
+    elements: &EmoryPageElements,
+    profile: BlockProfile,
+    rounds: usize,
+    timeout: Duration,
+) -> Result<ReloadComparison, Box<dyn std::error::Error>> {
+    let mut comparison = ReloadComparison {
+        profile,
+        unblocked: Vec::new(),
+        blocked: Vec::new(),
+    };
+    for _ in 0..rounds {
+        comparison
+            .unblocked
+            .push(timed_reload(page, elements, BlockProfile::None, timeout).await?);
+        comparison
+            .blocked
+            .push(timed_reload(page, elements, profile, timeout).await?);
+    }
+    Ok(comparison)
+}
+
+impl ToTable for ReloadComparison {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Blocking").add_attribute(Attribute::Bold),
+            Cell::new("Min"),
+            Cell::new("Median"),
+            Cell::new("Mean"),
+            Cell::new("Max"),
+        ]);
+        for (name, times) in [
+            ("none".to_string(), &self.unblocked),
+            (self.profile.to_string(), &self.blocked),
+        ] {
+            let mut sorted = times.clone();
+            sorted.sort();
+            let millis = |time: Option<Duration>| {
+                Cell::new(
+                    time.map(|time| format!("{}ms", time.as_millis()))
+                        .unwrap_or("-".to_string()),
+                )
+                .set_alignment(CellAlignment::Right)
+            };
+            let mean =
+                (!sorted.is_empty()).then(|| sorted.iter().sum::<Duration>() / sorted.len() as u32);
+            table.add_row(vec![
+                Cell::new(name),
+                millis(sorted.first().copied()),
+                millis(sorted.get(sorted.len() / 2).copied()),
+                millis(mean),
+                millis(sorted.last().copied()),
+            ]);
+        }
+        table
+    }
+}
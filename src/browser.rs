@@ -0,0 +1,311 @@
+use crate::args::{BrowserArgs, SniperArgs};
+use crate::elements::EmoryPageElements;
+use async_std::task::{sleep, JoinHandle};
+use chromiumoxide::cdp::browser_protocol::inspector::EventTargetCrashed;
+use chromiumoxide::cdp::browser_protocol::network::{Cookie, CookieParam, TimeSinceEpoch};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::{Browser, BrowserConfig, Page};
+use futures::StreamExt;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Builds the launch configuration for a new browser from the command line options.
+pub fn launch_config(args: &BrowserArgs, headed: bool) -> Result<BrowserConfig, String> {
+    let mut builder = BrowserConfig::builder();
+    if headed {
+        builder = builder.with_head();
+    }
+    if let Some(path) = &args.chrome_path {
+        builder = builder.chrome_executable(path);
+    }
+    if let Some(dir) = &args.user_data_dir {
+        builder = builder.user_data_dir(dir);
+    }
+    if let Some((width, height)) = args.window_size {
+        // let the page fill the window instead of the default emulated viewport
+        builder = builder.window_size(width, height).viewport(None);
+    }
+    if let Some(proxy) = &args.proxy {
+        builder = builder.arg(format!("--proxy-server={proxy}"));
+    }
+    if let Some(secs) = args.request_timeout {
+        builder = builder.request_timeout(Duration::from_secs(secs));
+    }
+    builder.args(&args.chrome_args).build()
+}
+
+/// The browser and the page the tool drives, watched for crashes and dropped connections.
+pub struct Session {
+    browser: Browser,
+    page: Page,
+    existing_page: bool,
+    connected: Arc<AtomicBool>,
+    crashed: Arc<AtomicBool>,
+    tasks: Vec<JoinHandle<()>>,
+    connect: Option<String>,
+    browser_args: BrowserArgs,
+    headed: bool,
+    relaunches: u32,
+}
+
+impl Session {
+    /// Launches a browser, or connects to the one given with `--connect`, and opens the page
+    /// to drive. A connected browser's registration tab is taken over as is.
+    pub async fn open(
+        args: &SniperArgs,
+        elements: &EmoryPageElements,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::start(
+            args.connect.clone(),
+            args.browser.clone(),
+            args.attach,
+            elements,
+        )
+        .await
+    }
+
+    async fn start(
+        connect: Option<String>,
+        browser_args: BrowserArgs,
+        headed: bool,
+        elements: &EmoryPageElements,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (mut browser, mut handler) = if let Some(url) = &connect {
+            Browser::connect(url).await?
+        } else {
+            Browser::launch(launch_config(&browser_args, headed)?).await?
+        };
+
+        // the handler stream ends, or its socket errors, once the browser is gone
+        let connected = Arc::new(AtomicBool::new(true));
+        let handler_connected = connected.clone();
+        let handler_task = async_std::task::spawn(async move {
+            loop {
+                match handler.next().await {
+                    None | Some(Err(CdpError::Ws(_))) => break,
+                    Some(_) => {}
+                }
+            }
+            handler_connected.store(false, Ordering::SeqCst);
+        });
+
+        // take over the registration tab of a connected browser, keeping its session
+        let existing_page = if connect.is_some() {
+            find_registration_page(&mut browser, elements).await?
+        } else {
+            // a persistent profile is kept as is so Duo still trusts it
+            if browser_args.user_data_dir.is_none() {
+                browser.clear_cookies().await?;
+            }
+            None
+        };
+        let is_existing_page = existing_page.is_some();
+        let page = match existing_page {
+            Some(page) => page,
+            None => {
+                let page = browser.new_page("about:blank").await?;
+                page.enable_stealth_mode().await?;
+                page
+            }
+        };
+
+        // a crashed renderer leaves the browser running but the page unusable
+        let mut crashes = page.event_listener::<EventTargetCrashed>().await?;
+        let crashed = Arc::new(AtomicBool::new(false));
+        let page_crashed = crashed.clone();
+        let crash_task = async_std::task::spawn(async move {
+            if crashes.next().await.is_some() {
+                page_crashed.store(true, Ordering::SeqCst);
+            }
+        });
+
+        Ok(Self {
+            browser,
+            page,
+            existing_page: is_existing_page,
+            connected,
+            crashed,
+            tasks: vec![handler_task, crash_task],
+            connect,
+            browser_args,
+            headed,
+            relaunches: 0,
+        })
+    }
+
+    pub fn page(&self) -> &Page {
+        &self.page
+    }
+
+    /// Whether the page is a tab of a connected browser that was already on the site.
+    pub fn is_existing_page(&self) -> bool {
+        self.existing_page
+    }
+
+    /// False once the browser connection dropped or the page crashed.
+    pub fn is_alive(&self) -> bool {
+        self.connected.load(Ordering::SeqCst) && !self.crashed.load(Ordering::SeqCst)
+    }
+
+    /// How many times the browser was relaunched, so far.
+    pub fn relaunches(&self) -> u32 {
+        self.relaunches
+    }
+
+    /// Every cookie in the browser, including the sign-in cookies of other domains.
+    pub async fn cookies(&self) -> Result<Vec<Cookie>, CdpError> {
+        self.browser.get_cookies().await
+    }
+
+    /// Sets previously saved cookies, e.g. to resume a signed in session.
+    pub async fn restore_cookies(&self, cookies: &[Cookie]) -> Result<(), Box<dyn Error>> {
+        if cookies.is_empty() {
+            return Ok(());
+        }
+        let cookies = cookies
+            .iter()
+            .map(cookie_param)
+            .collect::<Result<Vec<CookieParam>, String>>()?;
+        self.browser.set_cookies(cookies).await?;
+        Ok(())
+    }
+
+    /// Replaces a dead browser with a fresh one (or reconnects with `--connect`), restores the
+    /// cookies and loads the registration page again.
+    pub async fn relaunch(
+        &mut self,
+        elements: &EmoryPageElements,
+        cookies: &[Cookie],
+    ) -> Result<(), Box<dyn Error>> {
+        for task in self.tasks.drain(..) {
+            task.cancel().await;
+        }
+        if self.connect.is_none() {
+            // the process may be hung rather than gone
+            let _ = self.browser.kill().await;
+        }
+
+        let session = Self::start(
+            self.connect.clone(),
+            self.browser_args.clone(),
+            self.headed,
+            elements,
+        )
+        .await?;
+        let relaunches = self.relaunches + 1;
+        *self = session;
+        self.relaunches = relaunches;
+
+        self.restore_cookies(cookies).await?;
+        if !self.existing_page {
+            self.page.goto(elements.page_url).await?;
+        }
+        Ok(())
+    }
+
+    /// Closes a launched browser, leaving a browser we connected to running. A launched browser
+    /// that can't be closed over the connection, e.g. because it dropped, is killed instead.
+    pub async fn close(mut self) -> Result<(), Box<dyn Error>> {
+        if self.connect.is_none() {
+            // a crashed page still leaves Chromium running and answering
+            let closed =
+                self.connected.load(Ordering::SeqCst) && self.browser.close().await.is_ok();
+            if closed {
+                self.browser.wait().await?;
+            } else {
+                self.browser.kill().await.transpose()?;
+            }
+        }
+        for task in self.tasks.drain(..) {
+            task.cancel().await;
+        }
+        Ok(())
+    }
+}
+
+/// Writes cookies to a session file, readable only by the owner on Unix since it holds live
+/// sign in cookies.
+pub fn save_cookies(path: &Path, cookies: &[Cookie]) -> Result<(), Box<dyn Error>> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // the mode only applies to new files, an older session file may be readable by others
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options
+        .open(path)?
+        .write_all(serde_json::to_string_pretty(cookies)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn load_cookies(path: &Path) -> Result<Vec<Cookie>, Box<dyn Error>> {
+    let cookies = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read session {}: {e}", path.display()))?;
+    serde_json::from_str(&cookies)
+        .map_err(|e| format!("Invalid session {}: {e}", path.display()).into())
+}
+
+fn cookie_param(cookie: &Cookie) -> Result<CookieParam, String> {
+    let mut builder = CookieParam::builder()
+        .name(cookie.name.clone())
+        .value(cookie.value.clone())
+        .domain(cookie.domain.clone())
+        .path(cookie.path.clone())
+        .secure(cookie.secure)
+        .http_only(cookie.http_only)
+        .priority(cookie.priority.clone());
+    if let Some(same_site) = &cookie.same_site {
+        builder = builder.same_site(same_site.clone());
+    }
+    if !cookie.session {
+        builder = builder.expires(TimeSinceEpoch::new(cookie.expires));
+    }
+    builder.build()
+}
+
+// Finds a tab of a connected browser that is already on the registration site.
+async fn find_registration_page(
+    browser: &mut Browser,
+    elements: &EmoryPageElements,
+) -> Result<Option<Page>, CdpError> {
+    let host = elements
+        .page_url
+        .split('/')
+        .nth(2)
+        .unwrap_or(elements.page_url);
+    let mut targets: Vec<_> = browser
+        .fetch_targets()
+        .await?
+        .into_iter()
+        .filter(|target| target.r#type == "page" && target.url.contains(host))
+        .collect();
+    // prefer a tab already on the shopping cart
+    targets.sort_by_key(|target| !target.url.starts_with(elements.page_url));
+
+    let Some(target) = targets.into_iter().next() else {
+        return Ok(None);
+    };
+    // fetched targets are attached asynchronously
+    let start = Instant::now();
+    loop {
+        match browser.get_page(target.target_id.clone()).await {
+            Ok(page) => return Ok(Some(page)),
+            Err(e) => {
+                if start.elapsed() >= Duration::from_secs(5) {
+                    return Err(e);
+                }
+            }
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+}
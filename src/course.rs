@@ -0,0 +1,315 @@
+use serde::Serialize;
+use std::fmt;
+use std::iter::Sum;
+use std::str::FromStr;
+use std::ops::Add;
+
+/// PeopleSoft component codes that can follow the section in a description.
+const COMPONENTS: &[&str] = &[
+    "LEC", "LAB", "SEM", "DIS", "REC", "IND", "STU", "PRA", "CLN", "FLD", "RSC", "WKS", "TUT",
+    "ACT", "SUP", "CON", "INT", "THE",
+];
+
+/// The parts of a cart description such as "CS 170-1 LEC Intro to Computer Science (1234)".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CourseInfo {
+    pub subject: String,
+    pub catalog: String,
+    pub section: String,
+    /// Kind of meeting, e.g. LEC or LAB, when the description names it.
+    pub component: Option<String>,
+    pub class_number: Option<u32>,
+    pub title: String,
+}
+
+impl CourseInfo {
+    /// Returns None when the description has no "<catalog>-<section>" after the subject.
+    pub fn parse(description: &str) -> Option<Self> {
+        let class_number = class_number(description);
+        let description = description.trim();
+        let rest = match class_number {
+            Some(_) => description
+                .rsplit_once('(')
+                .map(|(rest, _)| rest)
+                .unwrap_or(description),
+            None => description,
+        };
+
+        let mut words = rest.split_whitespace();
+        let mut subject = Vec::new();
+        let (catalog, section) = loop {
+            let word = words.next()?;
+            // the catalog number is the first word starting with a digit, e.g. 170 or 170L
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                let (catalog, section) = word.split_once('-')?;
+                break (catalog.to_string(), section.to_string());
+            }
+            subject.push(word);
+        };
+        if subject.is_empty() {
+            return None;
+        }
+
+        let mut words = words.peekable();
+        let component = words
+            .next_if(|word| COMPONENTS.contains(word))
+            .map(str::to_string);
+
+        Some(Self {
+            subject: subject.join(" "),
+            catalog,
+            section,
+            component,
+            class_number,
+            title: words.collect::<Vec<&str>>().join(" "),
+        })
+    }
+
+    /// Subject and catalog number, e.g. "CS 170".
+    pub fn code(&self) -> String {
+        format!("{} {}", self.subject, self.catalog)
+    }
+}
+
+impl fmt::Display for CourseInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.code(), self.section)?;
+        if !self.title.is_empty() {
+            write!(f, " {}", self.title)?;
+        }
+        Ok(())
+    }
+}
+
+/// A course named on the command line, by class number or as "SUBJ NUM-SEC".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CourseQuery {
+    ClassNumber(u32),
+    Section {
+        subject: String,
+        catalog: String,
+        section: String,
+    },
+}
+
+impl CourseQuery {
+    pub fn matches(&self, info: &CourseInfo) -> bool {
+        match self {
+            CourseQuery::ClassNumber(number) => info.class_number == Some(*number),
+            CourseQuery::Section {
+                subject,
+                catalog,
+                section,
+            } => {
+                info.subject.eq_ignore_ascii_case(subject)
+                    && info.catalog.eq_ignore_ascii_case(catalog)
+                    && info.section.eq_ignore_ascii_case(section)
+            }
+        }
+    }
+}
+
+impl FromStr for CourseQuery {
+    type Err = String;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        if let Ok(number) = query.trim().parse() {
+            return Ok(CourseQuery::ClassNumber(number));
+        }
+        CourseInfo::parse(query)
+            .map(|info| CourseQuery::Section {
+                subject: info.subject,
+                catalog: info.catalog,
+                section: info.section,
+            })
+            .ok_or(format!(
+                "expected a class number or SUBJ NUM-SEC (e.g. \"CS 170-1\"), got `{query}`"
+            ))
+    }
+}
+
+impl fmt::Display for CourseQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CourseQuery::ClassNumber(number) => write!(f, "class {number}"),
+            CourseQuery::Section {
+                subject,
+                catalog,
+                section,
+            } => write!(f, "{subject} {catalog}-{section}"),
+        }
+    }
+}
+
+/// The class number in parentheses at the end of a description.
+pub fn class_number(description: &str) -> Option<u32> {
+    description
+        .trim()
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .and_then(|(_, number)| number.trim().parse().ok())
+}
+
+/// Credit hours of a course, a range for variable-credit courses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Credits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Credits {
+    /// Parses the credits column, e.g. "3.00" or "1.00 - 4.00".
+    pub fn parse(text: &str) -> Option<Self> {
+        let numbers: Vec<f32> = text
+            .split(|c: char| c.is_whitespace() || c == '-')
+            .filter_map(|word| word.parse().ok())
+            .collect();
+        Some(Self {
+            min: *numbers.first()?,
+            max: *numbers.last()?,
+        })
+    }
+}
+
+impl Add for Credits {
+    type Output = Credits;
+
+    fn add(self, other: Credits) -> Credits {
+        Credits {
+            min: self.min + other.min,
+            max: self.max + other.max,
+        }
+    }
+}
+
+impl Sum for Credits {
+    fn sum<I: Iterator<Item = Credits>>(iter: I) -> Credits {
+        iter.fold(Credits::default(), Add::add)
+    }
+}
+
+impl fmt::Display for Credits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}-{}", self.min, self.max)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_description() {
+        let info = CourseInfo::parse("CS 170-1 LEC Intro to Computer Science (1234)").unwrap();
+        assert_eq!(info.subject, "CS");
+        assert_eq!(info.catalog, "170");
+        assert_eq!(info.section, "1");
+        assert_eq!(info.component.as_deref(), Some("LEC"));
+        assert_eq!(info.class_number, Some(1234));
+        assert_eq!(info.title, "Intro to Computer Science");
+        assert_eq!(info.code(), "CS 170");
+    }
+
+    #[test]
+    fn keeps_catalog_suffix() {
+        let info = CourseInfo::parse("CS 170L-2 LAB Intro Lab (5678)").unwrap();
+        assert_eq!(info.catalog, "170L");
+        assert_eq!(info.section, "2");
+        assert_eq!(info.component.as_deref(), Some("LAB"));
+    }
+
+    #[test]
+    fn capitalized_title_word_is_not_a_component() {
+        let info = CourseInfo::parse("CS 170-1 API Design (1234)").unwrap();
+        assert_eq!(info.component, None);
+        assert_eq!(info.title, "API Design");
+    }
+
+    #[test]
+    fn parses_without_class_number() {
+        let info = CourseInfo::parse("MATH 111-3 LEC Calculus I").unwrap();
+        assert_eq!(info.class_number, None);
+        assert_eq!(info.title, "Calculus I");
+    }
+
+    #[test]
+    fn joins_multi_word_subject() {
+        let info = CourseInfo::parse("ENGRD LAB 101-1 SEM Design Studio (4321)").unwrap();
+        assert_eq!(info.subject, "ENGRD LAB");
+        assert_eq!(info.catalog, "101");
+        assert_eq!(info.component.as_deref(), Some("SEM"));
+    }
+
+    #[test]
+    fn rejects_unparseable_description() {
+        assert_eq!(CourseInfo::parse(""), None);
+        assert_eq!(CourseInfo::parse("Intro to Computer Science"), None);
+        assert_eq!(CourseInfo::parse("170-1 LEC No Subject"), None);
+        assert_eq!(CourseInfo::parse("CS 170 LEC No Section"), None);
+    }
+
+    #[test]
+    fn reads_class_number() {
+        assert_eq!(class_number("CS 170-1 LEC Intro (1234)"), Some(1234));
+        assert_eq!(class_number("CS 170-1 LEC Intro (1234) "), Some(1234));
+        assert_eq!(class_number("CS 170-1 LEC Intro"), None);
+        assert_eq!(class_number("CS 170-1 LEC Intro (TBA)"), None);
+    }
+
+    #[test]
+    fn parses_course_queries() {
+        assert_eq!("1234".parse(), Ok(CourseQuery::ClassNumber(1234)));
+        assert_eq!(
+            "cs 170L-2".parse(),
+            Ok(CourseQuery::Section {
+                subject: "cs".to_string(),
+                catalog: "170L".to_string(),
+                section: "2".to_string(),
+            })
+        );
+        assert!("CS 170".parse::<CourseQuery>().is_err());
+    }
+
+    #[test]
+    fn section_query_ignores_case() {
+        let info = CourseInfo::parse("CS 170L-2 LAB Intro Lab (5678)").unwrap();
+        assert!("cs 170l-2".parse::<CourseQuery>().unwrap().matches(&info));
+        assert!("5678".parse::<CourseQuery>().unwrap().matches(&info));
+        assert!(!"CS 170L-1".parse::<CourseQuery>().unwrap().matches(&info));
+    }
+
+    #[test]
+    fn parses_fixed_credits() {
+        assert_eq!(Credits::parse("3.00"), Some(Credits { min: 3.0, max: 3.0 }));
+        assert_eq!(Credits::parse("3 Units"), Some(Credits { min: 3.0, max: 3.0 }));
+    }
+
+    #[test]
+    fn parses_variable_credits() {
+        assert_eq!(Credits::parse("1.00 - 4.00"), Some(Credits { min: 1.0, max: 4.0 }));
+        assert_eq!(Credits::parse("1-4 Units"), Some(Credits { min: 1.0, max: 4.0 }));
+    }
+
+    #[test]
+    fn rejects_credits_without_numbers() {
+        assert_eq!(Credits::parse(""), None);
+        assert_eq!(Credits::parse("None"), None);
+    }
+
+    #[test]
+    fn sums_credit_ranges() {
+        let total: Credits = [
+            Credits { min: 3.0, max: 3.0 },
+            Credits { min: 1.0, max: 4.0 },
+        ]
+        .into_iter()
+        .sum();
+        assert_eq!(total, Credits { min: 4.0, max: 7.0 });
+        assert_eq!(total.to_string(), "4-7");
+        assert_eq!(Credits { min: 3.0, max: 3.0 }.to_string(), "3");
+    }
+}
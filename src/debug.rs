@@ -0,0 +1,231 @@
+use crate::elements::EmoryPageElements;
+use crate::har::NetworkRecorder;
+use async_std::sync::Mutex;
+use async_std::task::JoinHandle;
+use chromiumoxide::cdp::browser_protocol::log::EventEntryAdded;
+use chromiumoxide::cdp::js_protocol::runtime::{
+    EventConsoleApiCalled, EventExceptionThrown, RemoteObject,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+use chrono::{DateTime, Local};
+use futures::StreamExt;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+enum ConsoleEvent {
+    Console(Arc<EventConsoleApiCalled>),
+    Exception(Arc<EventExceptionThrown>),
+    Log(Arc<EventEntryAdded>),
+}
+
+/// Collects console output, uncaught exceptions and browser log entries for the page.
+pub struct ConsoleRecorder {
+    lines: Arc<Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl ConsoleRecorder {
+    pub async fn start(page: &Page) -> Result<Self, CdpError> {
+        let console = page
+            .event_listener::<EventConsoleApiCalled>()
+            .await?
+            .map(ConsoleEvent::Console);
+        let exceptions = page
+            .event_listener::<EventExceptionThrown>()
+            .await?
+            .map(ConsoleEvent::Exception);
+        let logs = page
+            .event_listener::<EventEntryAdded>()
+            .await?
+            .map(ConsoleEvent::Log);
+        let mut events =
+            futures::stream::select_all(vec![console.boxed(), exceptions.boxed(), logs.boxed()]);
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let task_lines = lines.clone();
+        let task = async_std::task::spawn(async move {
+            while let Some(event) = events.next().await {
+                let line = match event {
+                    ConsoleEvent::Console(event) => format!(
+                        "[{}] console.{}: {}",
+                        format_timestamp(*event.timestamp.inner()),
+                        event.r#type.as_ref(),
+                        event
+                            .args
+                            .iter()
+                            .map(remote_object_text)
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    ),
+                    ConsoleEvent::Exception(event) => format!(
+                        "[{}] exception: {}",
+                        format_timestamp(*event.timestamp.inner()),
+                        event
+                            .exception_details
+                            .exception
+                            .as_ref()
+                            .map(remote_object_text)
+                            .unwrap_or(event.exception_details.text.clone())
+                    ),
+                    ConsoleEvent::Log(event) => format!(
+                        "[{}] {} {}: {}{}",
+                        format_timestamp(*event.entry.timestamp.inner()),
+                        event.entry.source.as_ref(),
+                        event.entry.level.as_ref(),
+                        event.entry.text,
+                        event
+                            .entry
+                            .url
+                            .as_ref()
+                            .map(|url| format!(" ({url})"))
+                            .unwrap_or_default()
+                    ),
+                };
+                task_lines.lock().await.push(line);
+            }
+        });
+
+        Ok(Self { lines, task })
+    }
+
+    pub async fn lines(&self) -> Vec<String> {
+        self.lines.lock().await.clone()
+    }
+
+    pub async fn stop(self) {
+        self.task.cancel().await;
+    }
+}
+
+fn remote_object_text(object: &RemoteObject) -> String {
+    match (&object.value, &object.description) {
+        (Some(serde_json::Value::String(text)), _) => text.clone(),
+        (Some(value), _) => value.to_string(),
+        (None, Some(description)) => description.clone(),
+        (None, None) => object.r#type.as_ref().to_string(),
+    }
+}
+
+fn format_timestamp(millis: f64) -> String {
+    DateTime::from_timestamp_millis(millis as i64)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%H:%M:%S.%3f")
+                .to_string()
+        })
+        .unwrap_or("??:??:??".to_string())
+}
+
+/// Everything `--debug` records while the tool runs.
+pub struct DebugRecorder {
+    network: NetworkRecorder,
+    console: ConsoleRecorder,
+}
+
+impl DebugRecorder {
+    pub async fn start(page: &Page) -> Result<Self, CdpError> {
+        Ok(Self {
+            network: NetworkRecorder::start(page).await?,
+            console: ConsoleRecorder::start(page).await?,
+        })
+    }
+
+    /// Saves only the network capture, for runs that finished without an error.
+    pub async fn save_network(&self) -> Result<PathBuf, Box<dyn Error>> {
+        let path = PathBuf::from(format!(
+            "debug-{}.har",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        self.network.save(&path).await?;
+        Ok(path)
+    }
+
+    /// Writes a `debug-<timestamp>` directory with everything needed to diagnose a failed run.
+    ///
+    /// Each artifact is written independently, so one failing (e.g. a screenshot of a crashed
+    /// page) doesn't prevent the rest of the bundle from being saved. `relaunched` notes that
+    /// the recording stopped with the browser it started on.
+    pub async fn save_bundle(
+        &self,
+        page: &Page,
+        elements: &EmoryPageElements,
+        error: &dyn Error,
+        relaunched: bool,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let dir = PathBuf::from(format!("debug-{}", Local::now().format("%Y%m%d-%H%M%S")));
+        std::fs::create_dir_all(&dir)?;
+        let mut failures = Vec::new();
+
+        // error chain
+        let mut chain = format!("{error}\n");
+        let mut source = error.source();
+        while let Some(cause) = source {
+            let _ = writeln!(chain, "caused by: {cause}");
+            source = cause.source();
+        }
+        std::fs::write(dir.join("error.txt"), chain)?;
+
+        // current url
+        match page.url().await {
+            Ok(url) => std::fs::write(dir.join("url.txt"), url.unwrap_or_default())?,
+            Err(e) => failures.push(format!("url: {e}")),
+        }
+
+        // screenshot
+        if let Err(e) = page
+            .save_screenshot(
+                ScreenshotParams::builder().full_page(true).build(),
+                dir.join("screenshot.png"),
+            )
+            .await
+        {
+            failures.push(format!("screenshot: {e}"));
+        }
+
+        // dom
+        match page.content().await {
+            Ok(html) => std::fs::write(dir.join("page.html"), html)?,
+            Err(e) => failures.push(format!("page.html: {e}")),
+        }
+
+        // selectors
+        let mut selectors = String::new();
+        for found in elements.selector_report(page).await.0 {
+            let _ = writeln!(
+                selectors,
+                "{:<24} {:>3}  {}",
+                found.name, found.matches, found.selector
+            );
+        }
+        std::fs::write(dir.join("selectors.txt"), selectors)?;
+
+        // console and network
+        std::fs::write(
+            dir.join("console.log"),
+            self.console.lines().await.join("\n"),
+        )?;
+        if let Err(e) = self.network.save(dir.join("network.har")).await {
+            failures.push(format!("network.har: {e}"));
+        }
+        if relaunched {
+            failures.push(
+                "console.log, network.har: only cover the first browser, the run relaunched it"
+                    .to_string(),
+            );
+        }
+
+        if !failures.is_empty() {
+            std::fs::write(dir.join("bundle-errors.txt"), failures.join("\n"))?;
+        }
+        Ok(dir)
+    }
+
+    pub async fn stop(self) {
+        self.network.stop().await;
+        self.console.stop().await;
+    }
+}
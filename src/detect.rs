@@ -0,0 +1,228 @@
+use crate::selector::{self, Selector};
+use crate::shutdown;
+use crate::wait::{self, WaitError};
+use async_std::task::sleep;
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use futures::future::{self, BoxFuture, Either};
+use std::time::{Duration, Instant};
+
+/// Async check against the page that can't be expressed as a selector.
+pub type Predicate = Box<dyn for<'a> Fn(&'a Page) -> BoxFuture<'a, bool> + Send + Sync>;
+/// Async side effect run when a state is detected, before it is returned.
+pub type Action =
+    Box<dyn for<'a> Fn(&'a Page) -> BoxFuture<'a, Result<(), CdpError>> + Send + Sync>;
+
+pub enum Probe {
+    Selector(Selector),
+    Predicate(Predicate),
+}
+
+/// How the detector waits between checks.
+pub enum Poll {
+    /// Re-check on a fixed interval.
+    Interval(Duration),
+    /// Re-check as soon as the DOM changes, and at least every interval for predicates and
+    /// navigations the observer can't see.
+    Mutations(Duration),
+}
+
+struct Rule<S> {
+    probe: Probe,
+    state: S,
+    on_enter: Vec<Action>,
+}
+
+struct Notice {
+    selector: Selector,
+    handler: fn(&str),
+}
+
+/// Detects which of several pages the browser is on, from an ordered table of probes.
+///
+/// Rules are checked in the order they were added and the first match wins, so more specific
+/// pages (errors, prompts) should come before the pages they overlay.
+pub struct Detector<S> {
+    rules: Vec<Rule<S>>,
+    notices: Vec<Notice>,
+    timeout: Duration,
+    poll: Poll,
+}
+
+impl<S: Clone> Detector<S> {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            rules: Vec::new(),
+            notices: Vec::new(),
+            timeout,
+            poll: Poll::Interval(Duration::from_millis(100)),
+        }
+    }
+
+    /// Detects `state` when the selector matches.
+    pub fn state(self, selector: &Selector, state: S) -> Self {
+        self.rule(Probe::Selector(selector.clone()), state)
+    }
+
+    /// Detects `state` when the predicate holds.
+    pub fn state_when(self, predicate: Predicate, state: S) -> Self {
+        self.rule(Probe::Predicate(predicate), state)
+    }
+
+    pub fn rule(mut self, probe: Probe, state: S) -> Self {
+        self.rules.push(Rule {
+            probe,
+            state,
+            on_enter: Vec::new(),
+        });
+        self
+    }
+
+    /// Runs an action when the most recently added state is detected.
+    pub fn on_enter(mut self, action: Action) -> Self {
+        if let Some(rule) = self.rules.last_mut() {
+            rule.on_enter.push(action);
+        }
+        self
+    }
+
+    /// Calls the handler once with the text of the selector the first time it shows up,
+    /// without ending detection.
+    pub fn notice(mut self, selector: &Selector, handler: fn(&str)) -> Self {
+        self.notices.push(Notice {
+            selector: selector.clone(),
+            handler,
+        });
+        self
+    }
+
+    pub fn poll(mut self, poll: Poll) -> Self {
+        self.poll = poll;
+        self
+    }
+
+    /// Every selector the detector looks for, for error messages.
+    pub fn describe(&self) -> String {
+        self.rules
+            .iter()
+            .map(|rule| match &rule.probe {
+                Probe::Selector(selector) => selector.to_string(),
+                Probe::Predicate(_) => "<predicate>".to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Waits for one of the states, running its entry actions before returning it.
+    pub async fn detect(&self, page: &Page) -> Result<S, WaitError> {
+        match future::select(Box::pin(self.run(page)), Box::pin(shutdown::requested())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(WaitError::Cancelled),
+        }
+    }
+
+    async fn run(&self, page: &Page) -> Result<S, WaitError> {
+        let start = Instant::now();
+        let mut noticed = vec![false; self.notices.len()];
+        loop {
+            for (notice, noticed) in self.notices.iter().zip(noticed.iter_mut()) {
+                if *noticed {
+                    continue;
+                }
+                let Ok(Some(css)) = notice.selector.resolve(page).await else {
+                    continue;
+                };
+                if let Ok(element) = page.find_element(css).await {
+                    if let Some(text) = element.inner_text().await? {
+                        let text = text.trim();
+                        if !text.is_empty() {
+                            (notice.handler)(text);
+                            *noticed = true;
+                        }
+                    }
+                }
+            }
+
+            if let Some(rule) = self.matching_rule(page).await {
+                for action in &rule.on_enter {
+                    action(page).await?;
+                }
+                return Ok(rule.state.clone());
+            }
+
+            if start.elapsed() >= self.timeout {
+                return Err(WaitError::Timeout {
+                    selector: self.describe(),
+                    after: self.timeout,
+                });
+            }
+            let remaining = self.timeout.saturating_sub(start.elapsed());
+            match self.poll {
+                Poll::Interval(interval) => sleep(interval.min(remaining)).await,
+                Poll::Mutations(interval) => {
+                    if wait::for_mutation(page, interval.min(remaining))
+                        .await
+                        .is_err()
+                    {
+                        sleep(Duration::from_millis(100).min(remaining)).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn matching_rule(&self, page: &Page) -> Option<&Rule<S>> {
+        // a page mid navigation just doesn't match anything yet
+        let selectors: Vec<&Selector> = self
+            .rules
+            .iter()
+            .filter_map(|rule| match &rule.probe {
+                Probe::Selector(selector) => Some(selector),
+                Probe::Predicate(_) => None,
+            })
+            .collect();
+        let mut matches = selector::matching(page, &selectors)
+            .await
+            .unwrap_or_default()
+            .into_iter();
+
+        for rule in &self.rules {
+            let matched = match &rule.probe {
+                Probe::Selector(_) => matches.next().flatten().is_some(),
+                Probe::Predicate(predicate) => predicate(page).await,
+            };
+            if matched {
+                return Some(rule);
+            }
+        }
+        None
+    }
+}
+
+/// Entry action that clicks the element matching the selector.
+pub fn click(selector: &Selector) -> Action {
+    let selector = selector.clone();
+    Box::new(move |page| {
+        let selector = selector.clone();
+        Box::pin(async move {
+            page.find_element(selector.find(page).await?)
+                .await?
+                .click()
+                .await?;
+            Ok(())
+        })
+    })
+}
+
+/// Predicate that holds while the page url contains the fragment.
+pub fn url_contains(fragment: &'static str) -> Predicate {
+    Box::new(move |page| {
+        Box::pin(async move {
+            page.url()
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|url| url.contains(fragment))
+        })
+    })
+}
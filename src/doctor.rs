@@ -0,0 +1,236 @@
+use crate::elements::{EmoryPageElements, ToTable};
+use crate::selector::{find_candidate_js, Selector};
+use chromiumoxide::cdp::js_protocol::runtime::{CallArgument, CallFunctionOnParams};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use comfy_table::{Attribute, Cell, Color, Table};
+use serde::Deserialize;
+
+/// Longest sample text shown per element.
+const SAMPLE_CHARS: usize = 40;
+
+// Finds the first candidate with a match, and returns its index, how many nodes it matched and
+// the text (or value, or image source) of the first.
+const SAMPLE_SELECTOR: &str = concat!(
+    "function(selector){ const find = ",
+    find_candidate_js!(),
+    r#";
+        const found = find(selector);
+        if(!found) return null;
+        const first = found.nodes[0];
+        return {
+            index: found.index,
+            count: found.nodes.length,
+            sample: first.innerText || first.value || first.getAttribute("src") || ""
+        };
+    }"#
+);
+
+#[derive(Deserialize)]
+struct Sample {
+    index: usize,
+    count: usize,
+    sample: String,
+}
+
+struct Check {
+    stage: &'static str,
+    name: &'static str,
+    candidate: Option<usize>,
+    selector: String,
+    matches: usize,
+    sample: Option<String>,
+}
+
+// Where the browser was when the registration page was expected.
+struct UrlCheck {
+    stage: &'static str,
+    url: String,
+}
+
+/// Which page elements matched on the pages they belong to, for spotting site changes early.
+pub struct DoctorReport {
+    page_url: &'static str,
+    fields: Vec<Selector>,
+    urls: Vec<UrlCheck>,
+    checks: Vec<Check>,
+    notes: Vec<String>,
+}
+
+impl DoctorReport {
+    pub fn new(elements: &EmoryPageElements) -> Self {
+        let mut fields: Vec<Selector> = elements.selectors().into_iter().cloned().collect();
+        // the result icons are image paths, matched by source
+        fields.push(Selector::new(
+            "registration_success",
+            format!(r#"img[src*="{}"]"#, elements.registration_success),
+        ));
+        fields.push(Selector::new(
+            "registration_fail",
+            format!(r#"img[src*="{}"]"#, elements.registration_fail),
+        ));
+        Self {
+            page_url: elements.page_url,
+            fields,
+            urls: Vec::new(),
+            checks: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Checks the named fields against the page as it is now.
+    pub async fn check(&mut self, page: &Page, stage: &'static str, names: &[&'static str]) {
+        for name in names {
+            let Some(selector) = self.fields.iter().find(|field| field.name == *name) else {
+                continue;
+            };
+            let check = match sample(page, selector).await {
+                Ok(Some(found)) => {
+                    selector.used(found.index);
+                    Check {
+                        stage,
+                        name: selector.name,
+                        candidate: Some(found.index),
+                        selector: selector.candidates[found.index].to_string(),
+                        matches: found.count,
+                        sample: Some(found.sample),
+                    }
+                }
+                Ok(None) => Check {
+                    stage,
+                    name: selector.name,
+                    candidate: None,
+                    selector: selector.to_string(),
+                    matches: 0,
+                    sample: None,
+                },
+                Err(e) => Check {
+                    stage,
+                    name: selector.name,
+                    candidate: None,
+                    selector: selector.to_string(),
+                    matches: 0,
+                    sample: Some(format!("error: {e}")),
+                },
+            };
+            self.checks.push(check);
+        }
+    }
+
+    /// Checks that the browser is on the registration page.
+    pub async fn check_url(&mut self, page: &Page, stage: &'static str) {
+        let url = page.url().await.ok().flatten().unwrap_or_default();
+        self.urls.push(UrlCheck { stage, url });
+    }
+
+    /// Records a stage that couldn't be reached, leaving its fields unchecked.
+    pub fn note(&mut self, note: String) {
+        self.notes.push(note);
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+}
+
+async fn sample(page: &Page, selector: &Selector) -> Result<Option<Sample>, CdpError> {
+    let call = CallFunctionOnParams::builder()
+        .function_declaration(SAMPLE_SELECTOR)
+        .argument(
+            CallArgument::builder()
+                .value(serde_json::json!(selector))
+                .build(),
+        )
+        .build()
+        .map_err(CdpError::msg)?;
+    page.evaluate_function(call)
+        .await?
+        .into_value()
+        .map_err(CdpError::from)
+}
+
+impl ToTable for DoctorReport {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Element").add_attribute(Attribute::Bold),
+            Cell::new("Page"),
+            Cell::new("Matches"),
+            Cell::new("Candidate"),
+            Cell::new("Sample"),
+            Cell::new("Selector"),
+        ]);
+        if self.urls.is_empty() {
+            table.add_row(vec![
+                Cell::new("page_url"),
+                Cell::new("not seen").fg(Color::DarkGrey),
+                Cell::new("-").fg(Color::DarkGrey),
+                Cell::new(""),
+                Cell::new(""),
+                Cell::new(self.page_url),
+            ]);
+        }
+        for check in &self.urls {
+            let reached = check.url.starts_with(self.page_url);
+            table.add_row(vec![
+                Cell::new("page_url"),
+                Cell::new(check.stage),
+                Cell::new(if reached { "yes" } else { "no" }).fg(if reached {
+                    Color::Green
+                } else {
+                    Color::Red
+                }),
+                Cell::new(""),
+                Cell::new(&check.url),
+                Cell::new(self.page_url),
+            ]);
+        }
+        for field in &self.fields {
+            let checks: Vec<&Check> = self
+                .checks
+                .iter()
+                .filter(|check| check.name == field.name)
+                .collect();
+            if checks.is_empty() {
+                table.add_row(vec![
+                    Cell::new(field.name),
+                    Cell::new("not seen").fg(Color::DarkGrey),
+                    Cell::new("-").fg(Color::DarkGrey),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(field),
+                ]);
+            }
+            for check in checks {
+                let sample = check
+                    .sample
+                    .as_deref()
+                    .map(|text| {
+                        let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+                        match text.char_indices().nth(SAMPLE_CHARS) {
+                            Some((end, _)) => format!("{}…", &text[..end]),
+                            None => text,
+                        }
+                    })
+                    .unwrap_or_default();
+                table.add_row(vec![
+                    Cell::new(check.name),
+                    Cell::new(check.stage),
+                    Cell::new(check.matches).fg(if check.matches > 0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    }),
+                    // a fallback matching means the first candidate needs updating
+                    match check.candidate {
+                        Some(0) | None => Cell::new(""),
+                        Some(index) => Cell::new(format!("candidate {}", index + 1)).fg(Color::Yellow),
+                    },
+                    Cell::new(sample),
+                    Cell::new(&check.selector),
+                ]);
+            }
+        }
+        table
+    }
+}
@@ -1,66 +1,79 @@
+use crate::course::{self, CourseInfo, CourseQuery, Credits};
+use crate::schedule::{self, Meeting};
+use crate::selector::{self, Selector};
+use chromiumoxide::cdp::js_protocol::runtime::{CallArgument, CallFunctionOnParams};
 use chromiumoxide::{error::CdpError, Element, Page};
+use chrono::{NaiveTime, Timelike, Weekday};
 use comfy_table::{Attribute, Cell, Color, Table};
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 #[derive(Debug, Clone)]
 pub struct EmoryPageElements {
     pub page_url: &'static str,
-    pub username_input: &'static str,
-    pub passwd_input: &'static str,
-    pub login_error: &'static str,
-    pub validate_button: &'static str,
-    pub enroll_button: &'static str,
-    pub enroll_confirm_button: &'static str,
-    pub semester_cart: &'static str,
-    pub course_row: &'static str,
-    pub checkboxes: &'static str,
-    pub availability: &'static str,
-    pub description: &'static str,
-    pub schedule: &'static str,
-    pub room: &'static str,
-    pub instructor: &'static str,
-    pub credits: &'static str,
-    pub seats: &'static str,
-    pub results_rows: &'static str,
-    pub result_description: &'static str,
-    pub result_status: &'static str,
+    pub username_input: Selector,
+    pub passwd_input: Selector,
+    pub login_error: Selector,
+    pub validate_button: Selector,
+    pub enroll_button: Selector,
+    pub enroll_confirm_button: Selector,
+    pub semester_cart: Selector,
+    pub course_row: Selector,
+    pub checkboxes: Selector,
+    pub availability: Selector,
+    pub description: Selector,
+    pub schedule: Selector,
+    pub room: Selector,
+    pub instructor: Selector,
+    pub credits: Selector,
+    pub seats: Selector,
+    pub results_rows: Selector,
+    pub result_description: Selector,
+    pub result_status: Selector,
     pub registration_success: &'static str,
     pub registration_fail: &'static str,
-    pub duo_waiting: &'static str,
-    pub duo_trust_browser: &'static str,
-    pub duo_time_out_try_again: &'static str,
-    pub duo_verification_code: &'static str,
+    pub duo_waiting: Selector,
+    pub duo_trust_browser: Selector,
+    pub duo_time_out_try_again: Selector,
+    pub duo_verification_code: Selector,
 }
 
 impl Default for EmoryPageElements {
     fn default() -> Self {
         Self {
             page_url: "https://saprod.emory.edu/psc/saprod_48/EMPLOYEE/SA/c/SSR_STUDENT_FL.SSR_SHOP_CART_FL.GBL",
-            username_input: "input#userid",
-            passwd_input: "input#pwd",
-            login_error: "div#ptloginerrorcont",
-            validate_button: "a#DERIVED_SSR_FL_SSR_VALIDATE_FL",
-            enroll_button: "a#DERIVED_SSR_FL_SSR_ENROLL_FL",
-            enroll_confirm_button: r#"a[id="\#ICYes"]"#,
-            semester_cart: r#"a[id^="SSR_CART_TRM_FL_TERM_DESCR30$"]"#,
-            course_row: r#"tr[id^="SSR_REGFORM_VW$0_row_"]"#,
-            checkboxes: r#"input[type="checkbox"][id^="DERIVED_REGFRM1_SSR_SELECT$"]"#,
-            availability: r#"span[id^="DERIVED_SSR_FL_SSR_AVAIL_FL$"]"#,
-            description: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR80$"]"#,
-            schedule: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_SCHED_LONG$"]"#,
-            room: r#"span[id^="DERIVED_REGFRM1_SSR_MTG_LOC_LONG$"]"#,
-            instructor: r#"span[id^="DERIVED_REGFRM1_SSR_INSTR_LONG$"]"#,
-            credits: r#"span[id^="DERIVED_SSR_FL_SSR_UNITS_LBL$"]"#,
-            seats: r#"span[id^="DERIVED_SSR_FL_SSR_DESCR50$"]"#,
-            results_rows: r#"div[id^="win48div$ICField229_row$"]"#,
-            result_description: r#"span[id^="DERIVED_REGFRM1_DESCRLONG$"]"#,
-            result_status: r#"div[id^="win48divDERIVED_REGFRM1_SSR_STATUS_LONG$"]"#,
+            username_input: Selector::new("username_input", "input#userid").or(r#"input[name="userid"]"#),
+            passwd_input: Selector::new("passwd_input", "input#pwd").or(r#"input[name="pwd"]"#),
+            login_error: Selector::new("login_error", "div#ptloginerrorcont"),
+            validate_button: Selector::new("validate_button", "a#DERIVED_SSR_FL_SSR_VALIDATE_FL")
+                .or(r#"a[id$="SSR_VALIDATE_FL"]"#)
+                .or_containing("a.ps-button", "Validate"),
+            enroll_button: Selector::new("enroll_button", "a#DERIVED_SSR_FL_SSR_ENROLL_FL")
+                .or(r#"a[id$="SSR_ENROLL_FL"]"#)
+                .or_containing("a.ps-button", "Enroll"),
+            enroll_confirm_button: Selector::new("enroll_confirm_button", r#"a[id="\#ICYes"]"#)
+                .or_containing("a.ps-button", "Yes"),
+            semester_cart: Selector::new("semester_cart", r#"a[id^="SSR_CART_TRM_FL_TERM_DESCR30$"]"#),
+            course_row: Selector::new("course_row", r#"tr[id^="SSR_REGFORM_VW$0_row_"]"#),
+            checkboxes: Selector::new("checkboxes", r#"input[type="checkbox"][id^="DERIVED_REGFRM1_SSR_SELECT$"]"#),
+            availability: Selector::new("availability", r#"span[id^="DERIVED_SSR_FL_SSR_AVAIL_FL$"]"#),
+            description: Selector::new("description", r#"span[id^="DERIVED_SSR_FL_SSR_DESCR80$"]"#),
+            schedule: Selector::new("schedule", r#"span[id^="DERIVED_REGFRM1_SSR_MTG_SCHED_LONG$"]"#),
+            room: Selector::new("room", r#"span[id^="DERIVED_REGFRM1_SSR_MTG_LOC_LONG$"]"#),
+            instructor: Selector::new("instructor", r#"span[id^="DERIVED_REGFRM1_SSR_INSTR_LONG$"]"#),
+            credits: Selector::new("credits", r#"span[id^="DERIVED_SSR_FL_SSR_UNITS_LBL$"]"#),
+            seats: Selector::new("seats", r#"span[id^="DERIVED_SSR_FL_SSR_DESCR50$"]"#),
+            results_rows: Selector::new("results_rows", r#"div[id^="win48div$ICField229_row$"]"#),
+            result_description: Selector::new("result_description", r#"span[id^="DERIVED_REGFRM1_DESCRLONG$"]"#),
+            result_status: Selector::new("result_status", r#"div[id^="win48divDERIVED_REGFRM1_SSR_STATUS_LONG$"]"#),
             registration_success: "/cs/saprod/cache/PS_CS_STATUS_SUCCESS_ICN_1.gif",
             registration_fail: "/cs/saprod/cache/PS_CS_STATUS_ERROR_ICN_1.gif",
-            duo_waiting: "div#auth-view-wrapper:not(.auth-error)",
-            duo_trust_browser: r#"button[id="trust-browser-button"]"#,
-            duo_time_out_try_again: r#"button.try-again-button"#,
-            duo_verification_code: "div.verification-code",
+            duo_waiting: Selector::new("duo_waiting", "div#auth-view-wrapper:not(.auth-error)"),
+            duo_trust_browser: Selector::new("duo_trust_browser", r#"button[id="trust-browser-button"]"#)
+                .or_containing("button", "Yes, this is my device"),
+            duo_time_out_try_again: Selector::new("duo_time_out_try_again", r#"button.try-again-button"#),
+            duo_verification_code: Selector::new("duo_verification_code", "div.verification-code"),
         }
     }
 }
@@ -76,7 +89,46 @@ impl fmt::Display for ShoppingCart {
     }
 }
 
-#[derive(Debug)]
+/// The cart whose text is the term, ignoring case, or else the only cart the term matches as
+/// a case-insensitive regex.
+pub fn find_cart(mut carts: Vec<ShoppingCart>, term: &str) -> Result<ShoppingCart, String> {
+    let normalize = |text: &str| text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let available = |carts: &[ShoppingCart]| {
+        carts
+            .iter()
+            .map(|cart| format!("`{}`", normalize(&cart.text)))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let term = normalize(term);
+    if let Some(index) = carts
+        .iter()
+        .position(|cart| normalize(&cart.text).eq_ignore_ascii_case(&term))
+    {
+        return Ok(carts.swap_remove(index));
+    }
+
+    let pattern = RegexBuilder::new(&term)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Term `{term}` is not a cart name or a valid regex: {e}"))?;
+    let (mut matching, others): (Vec<ShoppingCart>, Vec<ShoppingCart>) = carts
+        .into_iter()
+        .partition(|cart| pattern.is_match(&normalize(&cart.text)));
+    match matching.len() {
+        1 => Ok(matching.remove(0)),
+        0 => Err(format!(
+            "No shopping cart matches term `{term}`. Available terms: {}",
+            available(&others)
+        )),
+        _ => Err(format!(
+            "Term `{term}` matches several shopping carts: {}",
+            available(&matching)
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub enum CourseStatus {
     Waitlist { position: u32 },
     Open { available: u32, capacity: u32 },
@@ -96,12 +148,15 @@ impl fmt::Display for CourseStatus {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Course {
-    pub checkbox_index: u8,
     pub availability: CourseStatus,
     pub description: String,
+    /// The description's parts, when it is in the usual format.
+    pub info: Option<CourseInfo>,
     pub schedule: String,
+    /// Meetings parsed from the schedule, empty when it is TBA.
+    pub meetings: Vec<Meeting>,
     pub room: String,
     pub instructor: String,
     pub credits: String,
@@ -113,9 +168,271 @@ impl fmt::Display for Course {
     }
 }
 
+impl Course {
+    pub fn id(&self) -> CourseId {
+        CourseId::from_description(&self.description)
+    }
+
+    /// Short name for messages, e.g. "CS 170-1".
+    pub fn label(&self) -> String {
+        match &self.info {
+            Some(info) => format!("{}-{}", info.code(), info.section),
+            None => self
+                .description
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" "),
+        }
+    }
+
+    /// Parsed credits, None when the column has no number.
+    pub fn credit_range(&self) -> Option<Credits> {
+        Credits::parse(&self.credits)
+    }
+
+    /// Whether any meeting of the two courses takes place at the same time.
+    pub fn conflicts_with(&self, other: &Course) -> bool {
+        self.meetings
+            .iter()
+            .any(|meeting| other.meetings.iter().any(|other| meeting.overlaps(other)))
+    }
+}
+
+/// Credits of all the courses together; courses without a number count as none.
+pub fn total_credits<'a>(courses: impl IntoIterator<Item = &'a Course>) -> Credits {
+    courses.into_iter().filter_map(Course::credit_range).sum()
+}
+
+/// The course matching each query, in query order. Errors with the courses in the cart when a
+/// query matches none of them.
+pub fn find_courses(courses: &[Course], queries: &[CourseQuery]) -> Result<Vec<Course>, String> {
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    for query in queries {
+        match courses.iter().find(|course| {
+            course
+                .info
+                .as_ref()
+                .is_some_and(|info| query.matches(info))
+        }) {
+            // two queries naming the same course would tick its checkbox twice, unticking it
+            Some(course) if found.iter().any(|other: &Course| other.id() == course.id()) => {}
+            Some(course) => found.push(course.clone()),
+            None => missing.push(query.to_string()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(format!(
+            "Not in the cart: {}. The cart has: {}",
+            missing.join(", "),
+            courses
+                .iter()
+                .map(|course| match course.info.as_ref().and_then(|info| info.class_number) {
+                    Some(number) => format!("{} ({number})", course.label()),
+                    None => course.label(),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+    Ok(found)
+}
+
+/// The course with each id, in id order and without duplicates, like `find_courses`.
+pub fn find_courses_by_id(courses: &[Course], ids: &[CourseId]) -> Result<Vec<Course>, String> {
+    let missing: Vec<String> = ids
+        .iter()
+        .filter(|id| !courses.iter().any(|course| course.id() == **id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("Planned courses not in the cart: {}", missing.join(", ")));
+    }
+    let mut found: Vec<Course> = Vec::new();
+    for id in ids {
+        if found.iter().all(|course| course.id() != *id) {
+            found.extend(courses.iter().find(|course| course.id() == *id).cloned());
+        }
+    }
+    Ok(found)
+}
+
+/// Every pair of courses whose meetings overlap.
+pub fn conflicts(courses: &[Course]) -> Vec<(&Course, &Course)> {
+    courses
+        .iter()
+        .enumerate()
+        .flat_map(|(i, course)| {
+            courses[i + 1..]
+                .iter()
+                .filter(|other| course.conflicts_with(other))
+                .map(move |other| (course, other))
+        })
+        .collect()
+}
+
+/// Identifies a course across reloads, wherever its row ends up in the cart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseId {
+    ClassNumber(u32),
+    /// The whole description, which includes the section, for rows without a class number.
+    Description(String),
+}
+
+impl CourseId {
+    /// Takes the class number from a description like "CS 170-1 Intro to CS (1234)".
+    pub fn from_description(description: &str) -> Self {
+        match course::class_number(description) {
+            Some(number) => CourseId::ClassNumber(number),
+            None => CourseId::Description(
+                description.split_whitespace().collect::<Vec<&str>>().join(" "),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CourseId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CourseId::ClassNumber(number) => write!(f, "class {}", number),
+            CourseId::Description(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+/// Selected courses that are no longer in the cart.
+#[derive(Debug)]
+pub struct MissingCourses(pub Vec<CourseId>);
+
+impl fmt::Display for MissingCourses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Selected courses are no longer in the cart: {}",
+            self.0
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for MissingCourses {}
+
+// Description text of every cart row, in row order, with the `$n` suffix of the row's own
+// checkbox id (`DERIVED_REGFRM1_SSR_SELECT$n`), or null when it has none.
+const ROW_DESCRIPTIONS: &str = r#"
+    function(rowSelector, descriptionSelector, checkboxSelector){
+        return Array.from(document.querySelectorAll(rowSelector)).map(row => {
+            const description = row.querySelector(descriptionSelector);
+            const checkbox = row.querySelector(checkboxSelector);
+            const select = checkbox ? checkbox.id.match(/\$(\d+)$/) : null;
+            return {
+                description: description ? description.innerText : "",
+                select: select ? Number(select[1]) : null,
+            };
+        });
+    }
+"#;
+
+// A cart row as read by `ROW_DESCRIPTIONS`.
+#[derive(Debug, Deserialize)]
+struct CartRow {
+    description: String,
+    select: Option<u32>,
+}
+
+// Checkbox number of each course's row, in the order of `ids`. Rows without a checkbox can't
+// be selected, so a course only found in one of them counts as missing.
+fn select_numbers(rows: &[CartRow], ids: &[CourseId]) -> Result<Vec<u32>, MissingCourses> {
+    let mut missing = Vec::new();
+    let mut numbers = Vec::new();
+    for id in ids {
+        let select = rows
+            .iter()
+            .filter(|row| CourseId::from_description(&row.description) == *id)
+            .find_map(|row| row.select);
+        match select {
+            Some(number) => numbers.push(number),
+            None => missing.push(id.clone()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(MissingCourses(missing));
+    }
+    Ok(numbers)
+}
+
 impl EmoryPageElements {
+    /// Every element selector, excluding the page url and icon paths.
+    pub fn selectors(&self) -> Vec<&Selector> {
+        vec![
+            &self.username_input,
+            &self.passwd_input,
+            &self.login_error,
+            &self.validate_button,
+            &self.enroll_button,
+            &self.enroll_confirm_button,
+            &self.semester_cart,
+            &self.course_row,
+            &self.checkboxes,
+            &self.availability,
+            &self.description,
+            &self.schedule,
+            &self.room,
+            &self.instructor,
+            &self.credits,
+            &self.seats,
+            &self.results_rows,
+            &self.result_description,
+            &self.result_status,
+            &self.duo_waiting,
+            &self.duo_trust_browser,
+            &self.duo_time_out_try_again,
+            &self.duo_verification_code,
+        ]
+    }
+
+    /// Which candidate of each selector matches the current page, and how many nodes it finds.
+    pub async fn selector_report(&self, page: &Page) -> SelectorReport {
+        let selectors = self.selectors();
+        let indexes = selector::matching(page, &selectors)
+            .await
+            .unwrap_or_else(|_| vec![None; selectors.len()]);
+        let mut report = Vec::new();
+        for (selector, index) in selectors.into_iter().zip(indexes) {
+            let (css, matches) = match index {
+                Some(index) => {
+                    let css = selector.css(index);
+                    let matches = page
+                        .find_elements(css.as_str())
+                        .await
+                        .map(|found| found.len())
+                        .unwrap_or(0);
+                    (selector.candidates[index].to_string(), matches)
+                }
+                None => (selector.to_string(), 0),
+            };
+            report.push(SelectorMatch {
+                name: selector.name,
+                selector: css,
+                matches,
+            });
+        }
+        SelectorReport(report)
+    }
+
+    /// Result icon paths the registration results are matched on.
+    pub fn status_icons(&self) -> Vec<&'static str> {
+        vec![self.registration_success, self.registration_fail]
+    }
+
     pub async fn get_shopping_carts(&self, page: &Page) -> Result<Vec<ShoppingCart>, CdpError> {
-        let semester_cart_elements = page.find_elements(self.semester_cart).await?;
+        let semester_cart_elements = page
+            .find_elements(self.semester_cart.find(page).await?)
+            .await?;
         let semester_carts: Vec<ShoppingCart> =
             futures::future::join_all(semester_cart_elements.into_iter().map(|cart| async move {
                 let text = cart.inner_text().await.unwrap().expect("test");
@@ -129,12 +446,39 @@ impl EmoryPageElements {
     }
 
     pub async fn get_cart_courses(&self, page: &Page) -> Result<Vec<Course>, CdpError> {
-        let course_row_elements = page.find_elements(self.course_row).await?;
+        let Some(course_row) = self.course_row.resolve(page).await? else {
+            return Ok(Vec::new());
+        };
+        let [seats, availability, description, schedule, instructor, room, credits] =
+            selector::find_all(
+                page,
+                [
+                    &self.seats,
+                    &self.availability,
+                    &self.description,
+                    &self.schedule,
+                    &self.instructor,
+                    &self.room,
+                    &self.credits,
+                ],
+            )
+            .await?;
+        // borrowed so every row's future can use them
+        let (seats, availability, description, schedule, instructor, room, credits) = (
+            &seats,
+            &availability,
+            &description,
+            &schedule,
+            &instructor,
+            &room,
+            &credits,
+        );
+        let course_row_elements = page.find_elements(course_row).await?;
         let courses: Vec<Course> =
-            futures::future::try_join_all(course_row_elements.into_iter().enumerate().map(
-                |(index, row)| async move {
+            futures::future::try_join_all(course_row_elements.into_iter().map(
+                |row| async move {
                     let nums: Vec<u32> = row
-                        .find_element(self.seats)
+                        .find_element(seats.as_str())
                         .await?
                         .inner_text()
                         .await?
@@ -144,7 +488,7 @@ impl EmoryPageElements {
                         .collect();
 
                     let course_status = match row
-                        .find_element(self.availability)
+                        .find_element(availability.as_str())
                         .await?
                         .inner_text()
                         .await?
@@ -176,35 +520,40 @@ impl EmoryPageElements {
                         _ => CourseStatus::Closed,
                     };
 
+                    let description = row
+                        .find_element(description.as_str())
+                        .await?
+                        .inner_text()
+                        .await?
+                        .unwrap_or("None".to_string());
+
+                    let schedule = row
+                        .find_element(schedule.as_str())
+                        .await?
+                        .inner_text()
+                        .await?
+                        .unwrap_or("None".to_string());
+
                     Ok::<Course, CdpError>(Course {
-                        checkbox_index: index as u8,
+                        meetings: schedule::parse(&schedule),
+                        schedule,
                         availability: course_status,
-                        description: row
-                            .find_element(self.description)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
-                        schedule: row
-                            .find_element(self.schedule)
-                            .await?
-                            .inner_text()
-                            .await?
-                            .unwrap_or("None".to_string()),
+                        info: CourseInfo::parse(&description),
+                        description,
                         instructor: row
-                            .find_element(self.instructor)
+                            .find_element(instructor.as_str())
                             .await?
                             .inner_text()
                             .await?
                             .unwrap_or("None".to_string()),
                         room: row
-                            .find_element(self.room)
+                            .find_element(room.as_str())
                             .await?
                             .inner_text()
                             .await?
                             .unwrap_or("None".to_string()),
                         credits: row
-                            .find_element(self.credits)
+                            .find_element(credits.as_str())
                             .await?
                             .inner_text()
                             .await?
@@ -217,22 +566,65 @@ impl EmoryPageElements {
         Ok(courses)
     }
 
+    /// Finds the checkbox number (`DERIVED_REGFRM1_SSR_SELECT$n`) of each course's row in a
+    /// single round trip, so a cart that changed since it was scraped can't shift a selection
+    /// onto the wrong course.
+    pub async fn locate_courses(
+        &self,
+        page: &Page,
+        ids: &[CourseId],
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let [course_row, description, checkboxes] = selector::find_all(
+            page,
+            [&self.course_row, &self.description, &self.checkboxes],
+        )
+        .await?;
+        let call = CallFunctionOnParams::builder()
+            .function_declaration(ROW_DESCRIPTIONS)
+            .argument(
+                CallArgument::builder()
+                    .value(serde_json::json!(course_row))
+                    .build(),
+            )
+            .argument(
+                CallArgument::builder()
+                    .value(serde_json::json!(description))
+                    .build(),
+            )
+            .argument(
+                CallArgument::builder()
+                    .value(serde_json::json!(checkboxes))
+                    .build(),
+            )
+            .build()
+            .map_err(CdpError::msg)?;
+        let rows: Vec<CartRow> = page.evaluate_function(call).await?.into_value()?;
+        Ok(select_numbers(&rows, ids)?)
+    }
+
     pub async fn get_registration_results(
         &self,
         page: &Page,
     ) -> Result<Vec<RegistrationResult>, CdpError> {
-        let result_elements = page.find_elements(self.results_rows).await?;
+        let Some(results_rows) = self.results_rows.resolve(page).await? else {
+            return Ok(Vec::new());
+        };
+        let [result_status, result_description] =
+            selector::find_all(page, [&self.result_status, &self.result_description]).await?;
+        // borrowed so every row's future can use them
+        let (result_status, result_description) = (&result_status, &result_description);
+        let result_elements = page.find_elements(results_rows).await?;
         let results: Vec<RegistrationResult> =
             futures::future::try_join_all(result_elements.into_iter().map(|result| async move {
                 let status_html = result
-                    .find_element(self.result_status)
+                    .find_element(result_status.as_str())
                     .await?
                     .inner_html()
                     .await?
                     .unwrap_or("".to_string());
                 Ok::<RegistrationResult, CdpError>(RegistrationResult {
                     description: result
-                        .find_element(self.result_description)
+                        .find_element(result_description.as_str())
                         .await?
                         .inner_text()
                         .await?
@@ -260,33 +652,207 @@ impl ToTable for Vec<Course> {
         let mut table = Table::new();
         table.set_header(vec![
             Cell::new("Course").add_attribute(Attribute::Bold),
+            Cell::new("Section"),
+            Cell::new("Type"),
+            Cell::new("Class"),
+            Cell::new("Title"),
             Cell::new("Credits"),
             Cell::new("Availability").add_attribute(Attribute::Bold),
             Cell::new("Schedule"),
             Cell::new("Room"),
             Cell::new("Instructor"),
+            Cell::new("Conflicts"),
         ]);
 
         for course in self {
+            // a description that doesn't parse is shown whole in place of the title
+            let (code, section, component, class_number, title) = match &course.info {
+                Some(info) => (
+                    info.code(),
+                    info.section.clone(),
+                    info.component.clone().unwrap_or_default(),
+                    info.class_number
+                        .map(|number| number.to_string())
+                        .unwrap_or_default(),
+                    info.title.clone(),
+                ),
+                None => (
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    course.description.clone(),
+                ),
+            };
             table.add_row(vec![
-                Cell::new(course.description.clone()).fg(Color::Green),
-                Cell::new(course.credits.clone()),
-                Cell::new(course.availability.to_string()).fg(Color::Green),
+                Cell::new(code).fg(Color::Green),
+                Cell::new(section),
+                Cell::new(component),
+                Cell::new(class_number),
+                Cell::new(title),
                 Cell::new(
+                    course
+                        .credit_range()
+                        .map(|credits| credits.to_string())
+                        .unwrap_or(course.credits.clone()),
+                ),
+                Cell::new(course.availability.to_string()).fg(Color::Green),
+                Cell::new(if course.meetings.is_empty() {
                     course
                         .schedule
                         .split_whitespace()
                         .collect::<Vec<&str>>()
-                        .join(" "),
-                ),
+                        .join(" ")
+                } else {
+                    course
+                        .meetings
+                        .iter()
+                        .map(|meeting| meeting.to_string())
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                }),
                 Cell::new(course.room.clone()),
                 Cell::new(course.instructor.clone()),
+                Cell::new(
+                    self.iter()
+                        .filter(|other| !std::ptr::eq(*other, course))
+                        .filter(|other| course.conflicts_with(other))
+                        .map(Course::label)
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                )
+                .fg(Color::Red),
             ]);
         }
         table
     }
 }
 
+pub struct SelectorMatch {
+    pub name: &'static str,
+    pub selector: String,
+    pub matches: usize,
+}
+
+pub struct SelectorReport(pub Vec<SelectorMatch>);
+
+impl ToTable for SelectorReport {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Element").add_attribute(Attribute::Bold),
+            Cell::new("Matches"),
+            Cell::new("Selector"),
+        ]);
+        for found in &self.0 {
+            table.add_row(vec![
+                Cell::new(found.name),
+                Cell::new(found.matches).fg(if found.matches > 0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                }),
+                Cell::new(&found.selector),
+            ]);
+        }
+        table
+    }
+}
+
+/// The cart's meetings as a week, one column per day and one row per half hour.
+pub struct WeekGrid<'a>(pub &'a [Course]);
+
+impl WeekGrid<'_> {
+    const SLOT_MINUTES: u32 = 30;
+}
+
+impl ToTable for WeekGrid<'_> {
+    fn to_table(&self) -> Table {
+        let meetings: Vec<(&Course, &Meeting)> = self
+            .0
+            .iter()
+            .flat_map(|course| course.meetings.iter().map(move |meeting| (course, meeting)))
+            .collect();
+        // weekend columns only when something meets then
+        let days: Vec<Weekday> = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ]
+        .into_iter()
+        .filter(|day| {
+            day.num_days_from_monday() < 5
+                || meetings.iter().any(|(_, meeting)| meeting.days.contains(day))
+        })
+        .collect();
+
+        let mut table = Table::new();
+        table.set_header(
+            std::iter::once(Cell::new("Time"))
+                .chain(days.iter().map(|day| Cell::new(day).add_attribute(Attribute::Bold)))
+                .collect::<Vec<Cell>>(),
+        );
+        let minutes = |time: NaiveTime| time.hour() * 60 + time.minute();
+        let (Some(first), Some(last)) = (
+            meetings.iter().map(|(_, meeting)| minutes(meeting.start)).min(),
+            meetings.iter().map(|(_, meeting)| minutes(meeting.end)).max(),
+        ) else {
+            return table;
+        };
+
+        let mut slot = first - first % Self::SLOT_MINUTES;
+        while slot < last {
+            let slot_end = slot + Self::SLOT_MINUTES;
+            let mut row = vec![Cell::new(
+                NaiveTime::from_hms_opt(slot / 60, slot % 60, 0)
+                    .map(|time| time.format("%-I:%M%p").to_string())
+                    .unwrap_or_default(),
+            )];
+            for day in &days {
+                let here: Vec<(&Course, &Meeting)> = meetings
+                    .iter()
+                    .filter(|(_, meeting)| {
+                        meeting.days.contains(day)
+                            && minutes(meeting.start) < slot_end
+                            && minutes(meeting.end) > slot
+                    })
+                    .copied()
+                    .collect();
+                // a block is labeled in its first slot and continued below it
+                let text = here
+                    .iter()
+                    .map(|(course, meeting)| {
+                        if minutes(meeting.start) >= slot {
+                            course.label()
+                        } else {
+                            "│".to_string()
+                        }
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let conflict = here.iter().enumerate().any(|(i, (course, _))| {
+                    here[i + 1..]
+                        .iter()
+                        .any(|(other, _)| course.conflicts_with(other))
+                });
+                row.push(if conflict {
+                    Cell::new(text).fg(Color::Red).add_attribute(Attribute::Bold)
+                } else {
+                    Cell::new(text).fg(Color::Green)
+                });
+            }
+            table.add_row(row);
+            slot = slot_end;
+        }
+        table
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub enum RegistrationStatus {
     Success,
     Fail,
@@ -303,6 +869,7 @@ impl fmt::Display for RegistrationStatus {
     }
 }
 
+#[derive(Debug, Serialize)]
 pub struct RegistrationResult {
     pub description: String,
     pub status: RegistrationStatus,
@@ -329,3 +896,109 @@ impl ToTable for Vec<RegistrationResult> {
         table
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(description: &str) -> Course {
+        Course {
+            availability: CourseStatus::Closed,
+            description: description.to_string(),
+            info: CourseInfo::parse(description),
+            schedule: "TBA".to_string(),
+            meetings: Vec::new(),
+            room: "TBA".to_string(),
+            instructor: "Staff".to_string(),
+            credits: "3.00".to_string(),
+        }
+    }
+
+    fn cart() -> Vec<Course> {
+        vec![
+            course("CS 170-1 LEC Intro (1234)"),
+            course("MATH 111-3 LEC Calculus I (5678)"),
+        ]
+    }
+
+    #[test]
+    fn finds_courses_in_query_order() {
+        let queries: Vec<CourseQuery> = vec!["5678".parse().unwrap(), "CS 170-1".parse().unwrap()];
+        let found = find_courses(&cart(), &queries).unwrap();
+        let ids: Vec<CourseId> = found.iter().map(Course::id).collect();
+        assert_eq!(ids, vec![CourseId::ClassNumber(5678), CourseId::ClassNumber(1234)]);
+    }
+
+    #[test]
+    fn queries_naming_the_same_course_find_it_once() {
+        let queries: Vec<CourseQuery> = vec![
+            "1234".parse().unwrap(),
+            "CS 170-1".parse().unwrap(),
+            "1234".parse().unwrap(),
+        ];
+        let found = find_courses(&cart(), &queries).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id(), CourseId::ClassNumber(1234));
+    }
+
+    #[test]
+    fn repeated_ids_find_the_course_once() {
+        let ids = [CourseId::ClassNumber(1234), CourseId::ClassNumber(1234)];
+        assert_eq!(find_courses_by_id(&cart(), &ids).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn missing_query_is_an_error() {
+        let queries: Vec<CourseQuery> = vec!["9999".parse().unwrap()];
+        assert!(find_courses(&cart(), &queries).is_err());
+    }
+
+    fn row(description: &str, select: Option<u32>) -> CartRow {
+        CartRow {
+            description: description.to_string(),
+            select,
+        }
+    }
+
+    #[test]
+    fn select_numbers_follow_reordered_cart() {
+        let rows = vec![
+            row("MATH 111-3 LEC Calculus I (5678)", Some(0)),
+            row("CS 170-1 LEC Intro (1234)", Some(1)),
+        ];
+        let ids = vec![CourseId::ClassNumber(1234), CourseId::ClassNumber(5678)];
+        assert_eq!(select_numbers(&rows, &ids).unwrap(), vec![1, 0]);
+    }
+
+    #[test]
+    fn select_numbers_report_missing_courses() {
+        let rows = vec![row("CS 170-1 LEC Intro (1234)", Some(0))];
+        let ids = vec![CourseId::ClassNumber(1234), CourseId::ClassNumber(5678)];
+        let missing = select_numbers(&rows, &ids).unwrap_err();
+        assert_eq!(missing.0, vec![CourseId::ClassNumber(5678)]);
+    }
+
+    #[test]
+    fn select_numbers_match_rows_without_class_number_by_description() {
+        let rows = vec![
+            row("CS 170-1 LEC Intro (1234)", Some(0)),
+            row("PE 101-2   LAB  Fitness", Some(1)),
+        ];
+        let ids = vec![CourseId::from_description("PE 101-2 LAB Fitness")];
+        assert_eq!(ids[0], CourseId::Description("PE 101-2 LAB Fitness".to_string()));
+        assert_eq!(select_numbers(&rows, &ids).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn select_numbers_use_checkbox_ids_not_row_positions() {
+        // the first row has no checkbox, so the second row's is number 0
+        let rows = vec![
+            row("CS 170-1 LEC Intro (1234)", None),
+            row("MATH 111-3 LEC Calculus I (5678)", Some(0)),
+        ];
+        let ids = vec![CourseId::ClassNumber(5678)];
+        assert_eq!(select_numbers(&rows, &ids).unwrap(), vec![0]);
+        let missing = select_numbers(&rows, &[CourseId::ClassNumber(1234)]).unwrap_err();
+        assert_eq!(missing.0, vec![CourseId::ClassNumber(1234)]);
+    }
+}
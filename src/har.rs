@@ -0,0 +1,393 @@
+use async_std::sync::Mutex;
+use async_std::task::JoinHandle;
+use chromiumoxide::cdp::browser_protocol::network::{
+    EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, EventResponseReceived,
+    GetRequestPostDataParams, GetResponseBodyParams, RequestId, Response,
+};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use chrono::DateTime;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Form fields whose values never make it into a HAR file: any field whose name contains one
+/// of these, ignoring case, so password change fields like `newpwd` are caught too.
+const REDACTED_FIELDS: &[&str] = &["pwd", "password"];
+/// Headers carrying the PeopleSoft and SSO session, which would sign in whoever reads the file.
+const REDACTED_HEADERS: &[&str] = &["cookie", "set-cookie", "authorization"];
+const REDACTED: &str = "REDACTED";
+
+enum NetworkEvent {
+    Request(Arc<EventRequestWillBeSent>),
+    Response(Arc<EventResponseReceived>),
+    Finished(Arc<EventLoadingFinished>),
+    Failed(Arc<EventLoadingFailed>),
+}
+
+struct PendingEntry {
+    started: f64,
+    wall_time: f64,
+    method: String,
+    url: String,
+    headers: Value,
+    has_post_data: bool,
+    response: Option<Response>,
+}
+
+/// Records the page's network traffic from CDP `Network` events for export as a HAR file.
+pub struct NetworkRecorder {
+    entries: Arc<Mutex<Vec<Value>>>,
+    task: JoinHandle<()>,
+}
+
+impl NetworkRecorder {
+    pub async fn start(page: &Page) -> Result<Self, CdpError> {
+        let requests = page
+            .event_listener::<EventRequestWillBeSent>()
+            .await?
+            .map(NetworkEvent::Request);
+        let responses = page
+            .event_listener::<EventResponseReceived>()
+            .await?
+            .map(NetworkEvent::Response);
+        let finished = page
+            .event_listener::<EventLoadingFinished>()
+            .await?
+            .map(NetworkEvent::Finished);
+        let failed = page
+            .event_listener::<EventLoadingFailed>()
+            .await?
+            .map(NetworkEvent::Failed);
+        let mut events = futures::stream::select_all(vec![
+            requests.boxed(),
+            responses.boxed(),
+            finished.boxed(),
+            failed.boxed(),
+        ]);
+
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let task_entries = entries.clone();
+        let page = page.clone();
+        let task = async_std::task::spawn(async move {
+            let mut pending: HashMap<RequestId, PendingEntry> = HashMap::new();
+            while let Some(event) = events.next().await {
+                match event {
+                    NetworkEvent::Request(event) => {
+                        // a redirect reuses the request id, so the previous hop ends here
+                        if let (Some(redirect), Some(entry)) =
+                            (&event.redirect_response, pending.remove(&event.request_id))
+                        {
+                            let mut entry = entry;
+                            entry.response = Some(redirect.clone());
+                            let har = har_entry(
+                                &page,
+                                &event.request_id,
+                                entry,
+                                *event.timestamp.inner(),
+                                None,
+                                false,
+                            )
+                            .await;
+                            task_entries.lock().await.push(har);
+                        }
+                        pending.insert(
+                            event.request_id.clone(),
+                            PendingEntry {
+                                started: *event.timestamp.inner(),
+                                wall_time: *event.wall_time.inner(),
+                                method: event.request.method.clone(),
+                                url: event.request.url.clone(),
+                                headers: event.request.headers.inner().clone(),
+                                has_post_data: event.request.has_post_data.unwrap_or(false),
+                                response: None,
+                            },
+                        );
+                    }
+                    NetworkEvent::Response(event) => {
+                        if let Some(entry) = pending.get_mut(&event.request_id) {
+                            entry.response = Some(event.response.clone());
+                        }
+                    }
+                    NetworkEvent::Finished(event) => {
+                        if let Some(entry) = pending.remove(&event.request_id) {
+                            let har = har_entry(
+                                &page,
+                                &event.request_id,
+                                entry,
+                                *event.timestamp.inner(),
+                                None,
+                                true,
+                            )
+                            .await;
+                            task_entries.lock().await.push(har);
+                        }
+                    }
+                    NetworkEvent::Failed(event) => {
+                        if let Some(entry) = pending.remove(&event.request_id) {
+                            let har = har_entry(
+                                &page,
+                                &event.request_id,
+                                entry,
+                                *event.timestamp.inner(),
+                                Some(&event.error_text),
+                                false,
+                            )
+                            .await;
+                            task_entries.lock().await.push(har);
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { entries, task })
+    }
+
+    /// Writes everything recorded so far as a HAR 1.2 file.
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let har = json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "course-sniper", "version": env!("CARGO_PKG_VERSION") },
+                "pages": [],
+                "entries": *self.entries.lock().await,
+            }
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&har)?)?;
+        Ok(())
+    }
+
+    pub async fn stop(self) {
+        self.task.cancel().await;
+    }
+}
+
+async fn har_entry(
+    page: &Page,
+    request_id: &RequestId,
+    entry: PendingEntry,
+    ended: f64,
+    error: Option<&str>,
+    with_body: bool,
+) -> Value {
+    let post_data = if entry.has_post_data {
+        page.execute(GetRequestPostDataParams::new(request_id.clone()))
+            .await
+            .ok()
+            .map(|data| data.result.post_data.clone())
+    } else {
+        None
+    };
+    let body = if with_body {
+        page.execute(GetResponseBodyParams::new(request_id.clone()))
+            .await
+            .ok()
+            .map(|body| (body.result.body.clone(), body.result.base64_encoded))
+    } else {
+        None
+    };
+
+    let request_mime = header_value(&entry.headers, "content-type").unwrap_or_default();
+    let mut request = json!({
+        "method": entry.method,
+        "url": redact_url(&entry.url),
+        "httpVersion": "HTTP/1.1",
+        "headers": har_headers(&entry.headers),
+        "queryString": query_string(&entry.url),
+        "cookies": [],
+        "headersSize": -1,
+        "bodySize": post_data.as_ref().map(|data| data.len() as i64).unwrap_or(0),
+    });
+    if let Some(data) = post_data {
+        request["postData"] = json!({
+            "mimeType": request_mime,
+            "text": redact_form(&data),
+        });
+    }
+
+    let response = match &entry.response {
+        Some(response) => {
+            let mut content = json!({
+                "size": body.as_ref().map(|(text, _)| text.len()).unwrap_or(0),
+                "mimeType": response.mime_type,
+            });
+            if let Some((text, base64)) = body {
+                content["text"] = json!(text);
+                if base64 {
+                    content["encoding"] = json!("base64");
+                }
+            }
+            json!({
+                "status": response.status,
+                "statusText": response.status_text,
+                "httpVersion": response.protocol.clone().unwrap_or("HTTP/1.1".to_string()),
+                "headers": har_headers(response.headers.inner()),
+                "cookies": [],
+                "content": content,
+                "redirectURL": header_value(response.headers.inner(), "location").unwrap_or_default(),
+                "headersSize": -1,
+                "bodySize": -1,
+            })
+        }
+        None => json!({
+            "status": 0,
+            "statusText": error.unwrap_or(""),
+            "httpVersion": "",
+            "headers": [],
+            "cookies": [],
+            "content": { "size": 0, "mimeType": "" },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+        }),
+    };
+
+    let time = ((ended - entry.started) * 1000.0).max(0.0);
+    let started = DateTime::from_timestamp_millis((entry.wall_time * 1000.0) as i64)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+    let mut har = json!({
+        "startedDateTime": started,
+        "time": time,
+        "request": request,
+        "response": response,
+        "cache": {},
+        "timings": { "send": 0, "wait": time, "receive": 0 },
+    });
+    if let Some(error) = error {
+        har["comment"] = json!(error);
+    }
+    har
+}
+
+fn har_headers(headers: &Value) -> Value {
+    headers
+        .as_object()
+        .map(|headers| {
+            headers
+                .iter()
+                .map(|(name, value)| {
+                    let value = if REDACTED_HEADERS
+                        .iter()
+                        .any(|header| name.eq_ignore_ascii_case(header))
+                    {
+                        REDACTED
+                    } else {
+                        value.as_str().unwrap_or_default()
+                    };
+                    json!({ "name": name, "value": value })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn header_value(headers: &Value, name: &str) -> Option<String> {
+    headers.as_object()?.iter().find_map(|(key, value)| {
+        key.eq_ignore_ascii_case(name)
+            .then(|| value.as_str().unwrap_or_default().to_string())
+    })
+}
+
+fn query_string(url: &str) -> Value {
+    url.split_once('?')
+        .map(|(_, query)| {
+            redact_form(query)
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    json!({ "name": name, "value": value })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn redact_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((base, query)) => format!("{base}?{}", redact_form(query)),
+        None => url.to_string(),
+    }
+}
+
+/// Replaces the values of sensitive fields in an urlencoded body or query string.
+fn redact_form(form: &str) -> String {
+    form.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_redacted_field(name) => format!("{name}={REDACTED}"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn is_redacted_field(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    REDACTED_FIELDS.iter().any(|field| name.contains(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_login_post_body() {
+        assert_eq!(
+            redact_form("timezoneOffset=300&userid=jdoe&pwd=hunter2&Submit=Sign+In"),
+            "timezoneOffset=300&userid=jdoe&pwd=REDACTED&Submit=Sign+In"
+        );
+    }
+
+    #[test]
+    fn redacts_query_string_password() {
+        let url = "https://saprod.emory.edu/psp/login?cmd=login&pwd=hunter2";
+        assert_eq!(
+            redact_url(url),
+            "https://saprod.emory.edu/psp/login?cmd=login&pwd=REDACTED"
+        );
+        assert_eq!(
+            query_string(url),
+            json!([
+                { "name": "cmd", "value": "login" },
+                { "name": "pwd", "value": REDACTED },
+            ])
+        );
+        assert_eq!(redact_url("https://example.com/plain"), "https://example.com/plain");
+    }
+
+    #[test]
+    fn redacts_fields_containing_pwd() {
+        assert_eq!(
+            redact_form("oldpwd=a&NEWPWD=b&newpassword2=c&pwdhint=d&userid=jdoe"),
+            "oldpwd=REDACTED&NEWPWD=REDACTED&newpassword2=REDACTED&pwdhint=REDACTED&userid=jdoe"
+        );
+    }
+
+    #[test]
+    fn redacts_session_headers() {
+        let headers = json!({
+            "Cookie": "PS_TOKEN=secret",
+            "set-cookie": "PS_TOKEN=secret; Path=/",
+            "Authorization": "Bearer secret",
+            "Content-Type": "text/html",
+        });
+        let headers = har_headers(&headers);
+        let value = |name: &str| {
+            headers
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|header| header["name"] == name)
+                .map(|header| header["value"].clone())
+                .unwrap()
+        };
+        assert_eq!(value("Cookie"), REDACTED);
+        assert_eq!(value("set-cookie"), REDACTED);
+        assert_eq!(value("Authorization"), REDACTED);
+        assert_eq!(value("Content-Type"), "text/html");
+    }
+}
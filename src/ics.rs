@@ -0,0 +1,284 @@
+use crate::elements::{Course, RegistrationResult, RegistrationStatus};
+use crate::course::CourseInfo;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Utc, Weekday};
+use clap::ValueEnum;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Weekly repeats for a meeting whose schedule has no date range, about one semester.
+const WEEKS_WITHOUT_DATES: u32 = 15;
+
+/// Which courses go into the exported calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IcsScope {
+    /// Every course in the cart
+    Cart,
+    /// Only courses that enrolled successfully
+    Enrolled,
+}
+
+/// Courses with a successful result, matched by class number or by subject, catalog number
+/// and section.
+pub fn enrolled<'a>(courses: &'a [Course], results: &[RegistrationResult]) -> Vec<&'a Course> {
+    courses
+        .iter()
+        .filter(|course| {
+            results.iter().any(|result| {
+                let Some((info, result_info)) = course
+                    .info
+                    .as_ref()
+                    .zip(CourseInfo::parse(&result.description))
+                else {
+                    return false;
+                };
+                let same_course = match (info.class_number, result_info.class_number) {
+                    (Some(number), Some(result_number)) => number == result_number,
+                    _ => {
+                        info.subject.eq_ignore_ascii_case(&result_info.subject)
+                            && info.catalog.eq_ignore_ascii_case(&result_info.catalog)
+                            && info.section.eq_ignore_ascii_case(&result_info.section)
+                    }
+                };
+                same_course && matches!(result.status, RegistrationStatus::Success)
+            })
+        })
+        .collect()
+}
+
+/// Writes an iCalendar file with a weekly repeating event per course meeting.
+pub fn save(path: &Path, courses: &[&Course]) -> std::io::Result<()> {
+    std::fs::write(path, calendar(courses))
+}
+
+fn calendar(courses: &[&Course]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//course-sniper//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for course in courses {
+        let summary = match &course.info {
+            Some(info) if !info.title.is_empty() => format!("{} {}", course.label(), info.title),
+            _ => course.label(),
+        };
+        let mut details = format!("Instructor: {}", collapse(&course.instructor));
+        if let Some(number) = course.info.as_ref().and_then(|info| info.class_number) {
+            let _ = write!(details, "\nClass number: {number}");
+        }
+
+        for (index, meeting) in course.meetings.iter().enumerate() {
+            // events start on the first meeting day of the range, or from today
+            let from = meeting
+                .dates
+                .map(|(first, _)| first)
+                .unwrap_or(Local::now().date_naive());
+            let Some(first_day) = first_meeting_day(from, &meeting.days) else {
+                continue;
+            };
+            let recurrence = match meeting.dates {
+                Some((_, last)) => format!("UNTIL={}T235959", last.format("%Y%m%d")),
+                None => format!("COUNT={}", WEEKS_WITHOUT_DATES as usize * meeting.days.len()),
+            };
+
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}-{index}@course-sniper", uid(course)),
+                format!("DTSTAMP:{stamp}"),
+                format!("DTSTART:{}", local_time(first_day, meeting.start)),
+                format!("DTEND:{}", local_time(first_day, meeting.end)),
+                format!(
+                    "RRULE:FREQ=WEEKLY;BYDAY={};{recurrence}",
+                    meeting
+                        .days
+                        .iter()
+                        .map(|day| by_day(*day))
+                        .collect::<Vec<&str>>()
+                        .join(",")
+                ),
+                format!("SUMMARY:{}", escape(&summary)),
+                format!("LOCATION:{}", escape(&collapse(&course.room))),
+                format!("DESCRIPTION:{}", escape(&details)),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<String>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn first_meeting_day(from: NaiveDate, days: &[Weekday]) -> Option<NaiveDate> {
+    (0..7)
+        .map(|offset| from + Duration::days(offset))
+        .find(|date| days.contains(&date.weekday()))
+}
+
+// Floating local time, shown at the same wall clock time in any calendar.
+fn local_time(date: NaiveDate, time: NaiveTime) -> String {
+    date.and_time(time).format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn by_day(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn uid(course: &Course) -> String {
+    match course.info.as_ref().and_then(|info| info.class_number) {
+        Some(number) => number.to_string(),
+        None => course
+            .label()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect(),
+    }
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Escapes text values as RFC 5545 requires.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// Folds lines longer than 75 octets onto continuation lines starting with a space.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elements::CourseStatus;
+    use crate::schedule;
+
+    fn course(description: &str, schedule: &str) -> Course {
+        Course {
+            availability: CourseStatus::Closed,
+            description: description.to_string(),
+            info: CourseInfo::parse(description),
+            schedule: schedule.to_string(),
+            meetings: schedule::parse(schedule),
+            room: "Math & Science Center E208".to_string(),
+            instructor: "Ada Lovelace".to_string(),
+            credits: "3.00".to_string(),
+        }
+    }
+
+    fn result(description: &str, status: RegistrationStatus) -> RegistrationResult {
+        RegistrationResult {
+            description: description.to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn matches_results_by_class_number() {
+        let courses = [course("CS 170-1 LEC Intro (1234)", "TBA")];
+        let results = [result("CS 170-1 LEC Intro (1234)", RegistrationStatus::Success)];
+        assert_eq!(enrolled(&courses, &results).len(), 1);
+        let results = [result("CS 170-1 LEC Intro (9999)", RegistrationStatus::Success)];
+        assert!(enrolled(&courses, &results).is_empty());
+    }
+
+    #[test]
+    fn section_prefix_is_not_a_match() {
+        let courses = [course("CS 170-1 LEC Intro", "TBA")];
+        let results = [result("CS 170-10 LEC Intro", RegistrationStatus::Success)];
+        assert!(enrolled(&courses, &results).is_empty());
+        let results = [result("cs 170-1 LEC Intro", RegistrationStatus::Success)];
+        assert_eq!(enrolled(&courses, &results).len(), 1);
+    }
+
+    #[test]
+    fn failed_results_are_not_enrolled() {
+        let courses = [course("CS 170-1 LEC Intro (1234)", "TBA")];
+        let results = [result("CS 170-1 LEC Intro (1234)", RegistrationStatus::Fail)];
+        assert!(enrolled(&courses, &results).is_empty());
+    }
+
+    #[test]
+    fn escapes_text_values() {
+        assert_eq!(escape("a;b,c\\d\ne"), "a\\;b\\,c\\\\d\\ne");
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let line = "x".repeat(160);
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), 75);
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ') && part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+        assert_eq!(fold("short"), "short");
+    }
+
+    #[test]
+    fn folds_without_splitting_characters() {
+        let line = "é".repeat(60);
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn writes_weekly_events() {
+        let cs = course(
+            "CS 170-1 LEC Intro, Part 1 (1234)",
+            "MoWe 10:00AM - 11:15AM 01/13/2027 - 04/28/2027",
+        );
+        let calendar = calendar(&[&cs]);
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert!(calendar.contains("UID:1234-0@course-sniper\r\n"));
+        // 13 January 2027 is a Wednesday
+        assert!(calendar.contains("DTSTART:20270113T100000\r\n"));
+        assert!(calendar.contains("DTEND:20270113T111500\r\n"));
+        assert!(calendar.contains("RRULE:FREQ=WEEKLY;BYDAY=MO,WE;UNTIL=20270428T235959\r\n"));
+        assert!(calendar.contains("SUMMARY:CS 170-1 Intro\\, Part 1\r\n"));
+        assert!(calendar.contains("LOCATION:Math & Science Center E208\r\n"));
+    }
+
+    #[test]
+    fn repeats_undated_meetings_for_a_semester() {
+        let cs = course("CS 170-1 LEC Intro (1234)", "TuTh 1:00PM - 2:15PM");
+        assert!(calendar(&[&cs]).contains(";COUNT=30\r\n"));
+    }
+
+    #[test]
+    fn skips_courses_without_meetings() {
+        let cs = course("CS 170-1 LEC Intro (1234)", "TBA");
+        assert!(!calendar(&[&cs]).contains("BEGIN:VEVENT"));
+    }
+}
@@ -1,135 +1,954 @@
 use async_std::task::sleep;
-use chromiumoxide::error::CdpError;
-use chromiumoxide::page::ScreenshotParams;
-use chromiumoxide::{Browser, BrowserConfig, Element, Page};
+use browser::Session;
+use chromiumoxide::Page;
+use chromiumoxide::cdp::browser_protocol::network::Cookie;
 use chromiumoxide::cdp::js_protocol::runtime::{CallFunctionOnParams, CallArgument};
-use chrono::{Local, Timelike};
-use clap::Parser;
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Timelike};
+use clap::{CommandFactory, FromArgMatches};
 use core::fmt;
-use elements::{EmoryPageElements, ToTable};
-use futures::StreamExt;
+use detect::{Detector, Poll};
+use doctor::DoctorReport;
+use elements::{
+    Course, CourseId, CourseStatus, EmoryPageElements, RegistrationResult, ToTable, WeekGrid,
+};
+use futures::future::{self, Either};
+use futures::Future;
 use indicatif::{ProgressBar, ProgressStyle};
-use inquire::{MultiSelect, Password, PasswordDisplayMode, Select, Text};
+use inquire::{Confirm, MultiSelect, Password, PasswordDisplayMode, Select, Text};
 use std::borrow::Cow;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::time::Duration;
+use timing::{Checkpoint, RunReport, RunTimings};
+use wait::WaitError;
 
 mod args;
-use args::SniperArgs;
+use args::{Command, CreditCheck, SniperArgs, Timeouts, WatchArgs};
+use blocking::{BlockProfile, ResourceBlocker};
+use ics::IcsScope;
+use plan::{Action, Method, Plan};
 
 mod ascii;
+mod blocking;
+mod browser;
+mod course;
+mod debug;
+mod detect;
+mod doctor;
 mod elements;
+mod har;
+mod ics;
+mod pause;
+mod plan;
+mod schedule;
+mod selector;
+mod shutdown;
+mod timing;
+mod wait;
 
-const TIMEOUT: u64 = 120;
+/// Relaunches tried in a row before giving up on a lost browser.
+const MAX_RECOVERY_ATTEMPTS: u32 = 3;
+/// How long before registration the wait stops sleeping and only yields.
+const SPIN_WINDOW: Duration = Duration::from_secs(10);
+/// Longest sleep while waiting for registration, between browser checks and cookie refreshes.
+const WAIT_STEP: Duration = Duration::from_secs(4);
 
 #[async_std::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // get args
-    let cli_args = SniperArgs::parse();
+    let matches = SniperArgs::command().get_matches();
+    let mut cli_args = SniperArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // a saved plan answers the prompts, flags still take precedence
+    let plan = cli_args.plan.as_deref().map(Plan::load).transpose()?;
+    cli_args.timeouts.apply(
+        &matches,
+        &plan.as_ref().map(|plan| plan.timeouts.clone()).unwrap_or_default(),
+    )?;
+    shutdown::install()?;
+    // an unrecognized page can only be fixed by hand with someone at the terminal
+    if !cli_args.no_pause && std::io::stdin().is_terminal() {
+        pause::enable(cli_args.attach || cli_args.connect.is_some());
+    }
 
     println!("\n{}\n", ascii::BANNER);
     println!("Welcome to course-sniper, the precision registration tool.");
 
-    let pb = get_progress_bar("Enabling browser...");
+    // page elements
+    let elements = elements::EmoryPageElements::default();
 
-    // setup browser
-    let (mut browser, mut handler) = if cli_args.attach {
-        Browser::launch(BrowserConfig::builder().with_head().build()?).await?
-    } else {
-        Browser::launch(BrowserConfig::builder().build()?).await?
+    // Ctrl-C drops the setup and run future, cancelling whatever it was waiting on
+    let mut session = None;
+    let mut recorder = None;
+    let mut report = RunReport::default();
+    let started = async {
+        // setup browser
+        let pb = get_progress_bar("Enabling browser...");
+        let session = session.insert(Session::open(&cli_args, &elements).await?);
+        pb.finish_with_message(if cli_args.connect.is_some() {
+            "Connected to browser."
+        } else {
+            "Browser enabled."
+        });
+
+        // record network traffic and console output from the first request on
+        if cli_args.debug {
+            recorder = Some(debug::DebugRecorder::start(session.page()).await?);
+        }
+        // resume a session saved by the login command
+        if let Some(path) = &cli_args.session {
+            session.restore_cookies(&browser::load_cookies(path)?).await?;
+        }
+        if !session.is_existing_page() {
+            session.page().goto(elements.page_url).await?;
+        }
+
+        run(session, elements.clone(), &cli_args, plan, &mut report).await
+    };
+    let result = match future::select(Box::pin(started), Box::pin(shutdown::requested())).await
+    {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(shutdown::Interrupted.into()),
     };
+    let interrupted = matches!(&result, Err(e) if shutdown::is_interrupt(e.as_ref()));
+    if interrupted {
+        println!("\nInterrupted, shutting down...");
+    }
 
-    let running = Arc::new(AtomicBool::new(true));
-    let running_clone = running.clone();
+    if report.is_started() {
+        match report.save() {
+            Ok(path) => println!("Run report saved to {}", path.display()),
+            Err(e) => println!("Failed to save run report: {e}"),
+        }
+    }
 
-    let handle = async_std::task::spawn(async move {
-        while running_clone.load(Ordering::Relaxed) {
-            if let Some(event) = handler.next().await {
-                let _ = event;
+    if let (Some(recorder), Some(session)) = (recorder, &session) {
+        if session.relaunches() > 0 {
+            println!("The browser was relaunched, the debug recording only covers the first one.");
+        }
+        match &result {
+            Ok(_) => match recorder.save_network().await {
+                Ok(path) => println!("Network capture saved to {}", path.display()),
+                Err(e) => println!("Failed to save network capture: {e}"),
+            },
+            Err(e) => match recorder
+                .save_bundle(session.page(), &elements, e.as_ref(), session.relaunches() > 0)
+                .await
+            {
+                Ok(path) => println!("Debug bundle saved to {}", path.display()),
+                Err(e) => println!("Failed to save debug bundle: {e}"),
+            },
+        }
+        recorder.stop().await;
+    }
+
+    // cleanup, a run that already failed or was interrupted keeps its own outcome
+    if let Some(session) = session {
+        if let Err(e) = session.close().await {
+            if !interrupted && result.is_ok() {
+                return Err(e);
             }
+            println!("Failed to close the browser: {e}");
         }
-    });
+    }
 
-    browser.clear_cookies().await?;
-    pb.finish_with_message("Browser enabled.");
+    if interrupted {
+        std::process::exit(shutdown::EXIT_CODE);
+    }
+    result
+}
 
-    // page elements
-    let elements = elements::EmoryPageElements::default();
+async fn run(
+    session: &mut Session,
+    elements: EmoryPageElements,
+    args: &SniperArgs,
+    plan: Option<Plan>,
+    report: &mut RunReport,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeouts = &args.timeouts;
+    let page = session.page().clone();
+    let page = &page;
+    if let Some(Command::Doctor) = &args.command {
+        return doctor(page, &elements, args).await;
+    }
+    // kept in memory so a relaunched browser can sign in again
+    let mut credentials = sign_in(page, &elements, timeouts).await?;
+
+    if let Some(Command::Login(login)) = &args.command {
+        browser::save_cookies(&login.output, &session.cookies().await?)?;
+        println!(
+            "Session saved to {}, use it with --session (it holds sign in cookies, keep it private).",
+            login.output.display()
+        );
+        return Ok(());
+    }
+
+    // pick a shopping cart
+    let term = args
+        .term
+        .clone()
+        .or(plan.as_ref().and_then(|plan| plan.term.clone()));
+    let cart = enter_cart(page, &elements, None, term.as_deref(), timeouts).await?;
+
+    // get course info
+    let pb = get_progress_bar("Fetching courses in cart...");
+    wait::for_selector(page, &elements.course_row, timeouts.courses).await?;
+    let courses = elements.get_cart_courses(page).await?;
+    pb.finish_with_message(format!("Found {} courses.", courses.len()));
+    println!("{}", courses.to_table());
+    if let (Some(path), IcsScope::Cart) = (&args.export_ics, args.ics_scope) {
+        export_ics(path, &courses.iter().collect::<Vec<&Course>>());
+    }
+
+    // the cart as a week, so a selection can be judged before committing to it
+    if courses.iter().any(|course| !course.meetings.is_empty()) {
+        println!("{}", WeekGrid(&courses).to_table());
+    }
+    if let Some(Command::Cart) = &args.command {
+        skip_enrolled_ics(args);
+        return Ok(());
+    }
+
+    // pick courses
+    let selected_courses = pick_courses(
+        &courses,
+        args,
+        plan.as_ref().map(|plan| plan.courses.as_slice()),
+    )?;
+    // rows are matched up again after every reload in case the cart changed
+    let selected_ids: Vec<CourseId> = selected_courses.iter().map(Course::id).collect();
+
+    if let Some(Command::Watch(watch_args)) = &args.command {
+        return watch(page, &elements, args, watch_args, cart.as_deref(), selected_courses).await;
+    }
+
+    // pick validate, enroll or a reload benchmark
+    let action = match (&args.command, &plan) {
+        (Some(Command::Validate), _) => Action::Validate,
+        (Some(Command::Enroll(_)), _) => Action::Enroll,
+        (Some(Command::Bench(_)), _) => Action::Benchmark,
+        (_, Some(plan)) => plan.action,
+        _ => Select::new("Select action:", Action::all()).prompt()?,
+    };
+    if action != Action::Enroll {
+        skip_enrolled_ics(args);
+    }
+    let enroll_args = match &args.command {
+        Some(Command::Enroll(enroll_args)) => Some(enroll_args),
+        _ => None,
+    };
+    let enrollment = if action == Action::Enroll {
+        let method = match enroll_args
+            .and_then(|enroll_args| enroll_args.method)
+            .or(plan.as_ref().and_then(|plan| plan.method))
+        {
+            Some(method) => method,
+            None => Select::new("Choose enrollment method:", Method::all()).prompt()?,
+        };
+        let time = match enroll_args
+            .and_then(|enroll_args| enroll_args.at)
+            .or(plan.as_ref().and_then(|plan| plan.time))
+        {
+            Some(time) => RegistrationTime::from(time),
+            None => {
+                //TODO improve registration time selection and implimentation
+                let registration_times: Vec<RegistrationTime> = (1..=12)
+                    .flat_map(|hour| {
+                        (0..60).flat_map(move |minute| {
+                            [true, false]
+                                .iter()
+                                .map(move |&am| RegistrationTime(hour, minute, am))
+                        })
+                    })
+                    .collect();
+                Select::new("Select registration time:", registration_times).prompt()?
+            }
+        };
+        Some(Enrollment { method, time })
+    } else {
+        None
+    };
+
+    if let Some(path) = &args.save_plan {
+        let plan = Plan {
+            term: cart.clone(),
+            courses: selected_ids.clone(),
+            action,
+            method: enrollment.as_ref().map(|enrollment| enrollment.method),
+            time: enrollment.as_ref().map(|enrollment| enrollment.time.to_time()),
+            timeouts: timeouts.chosen.clone(),
+        };
+        match plan.save(path) {
+            Ok(()) => println!("Plan saved to {}", path.display()),
+            Err(e) => println!("Failed to save plan: {e}"),
+        }
+    }
 
-    let page = browser.new_page(elements.page_url).await?;
-    page.enable_stealth_mode().await?;
+    if action == Action::Benchmark {
+        let (rounds, profile) = match &args.command {
+            Some(Command::Bench(bench_args)) => (bench_args.rounds, bench_args.profile),
+            _ => (5, None),
+        };
+        let profile = match profile.unwrap_or(args.block_resources) {
+            BlockProfile::None => Select::new(
+                "Compare against blocking:",
+                vec![BlockProfile::Media, BlockProfile::All],
+            )
+            .prompt()?,
+            profile => profile,
+        };
+        let pb = get_progress_bar("Timing cart reloads...");
+        let comparison =
+            blocking::compare_reload(page, &elements, profile, rounds, timeouts.selection)
+                .await?;
+        pb.finish_with_message("Reload benchmark finished.");
+        println!("{}", comparison.to_table());
+    } else if let Some(enrollment) = enrollment {
+        let target = Target {
+            cart,
+            courses: selected_courses,
+            ids: selected_ids,
+        };
+        enroll(session, &elements, args, report, &mut credentials, &target, enrollment).await?;
+    } else {
+        let pb = get_progress_bar("Selecting courses...");
+        select_courses(page, &elements, &selected_ids, timeouts.selection).await?;
+        pb.finish_with_message("Courses selected.");
 
-    match run(&page, elements).await {
-        Ok(_) => (),
+        // validate
+        wait::for_element(page, &elements.validate_button, timeouts.enroll)
+            .await?
+            .click()
+            .await?;
+
+        println!(
+            "Validation clicked at {}",
+            Local::now().format("%H:%M:%S.%3f")
+        );
+        // results
+        let pb = get_progress_bar("Waiting for validation results...");
+        wait::for_selector(page, &elements.results_rows, timeouts.results).await?;
+        let registration_results = elements.get_registration_results(page).await?;
+        pb.finish_with_message(format!(
+            "Found {} validation results.",
+            registration_results.len()
+        ));
+        println!("{}", registration_results.to_table());
+    }
+
+    Ok(())
+}
+
+// Signs in unless the browser already is, e.g. through --connect or --session. Returns the
+// credentials entered, if any.
+async fn sign_in(
+    page: &Page,
+    elements: &EmoryPageElements,
+    timeouts: &Timeouts,
+) -> Result<Option<Credentials>, Box<dyn std::error::Error>> {
+    let status = transition(page, None, || session_transition(page, elements, timeouts.login)).await?;
+    if let SessionTransition::SignedIn = status {
+        println!("Already signed in, skipping login.");
+        return Ok(None);
+    }
+    let credentials = Credentials::prompt()?;
+    if !login(page, elements, &credentials, timeouts).await? {
+        return Err("Login failed".into());
+    }
+    Ok(Some(credentials))
+}
+
+// Walks from the login page through the cart, validation results and the enrollment
+// confirmation, checking each page's selectors where they should match. The confirmation is
+// left unanswered, so nothing is enrolled.
+async fn doctor(
+    page: &Page,
+    elements: &EmoryPageElements,
+    args: &SniperArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeouts = &args.timeouts;
+    let mut report = DoctorReport::new(elements);
+
+    if let SessionTransition::SignedOut =
+        transition(page, None, || session_transition(page, elements, timeouts.login)).await?
+    {
+        report
+            .check(page, "login", &["username_input", "passwd_input"])
+            .await;
+    }
+    sign_in(page, elements, timeouts).await?;
+
+    if let CartTransition::Select =
+        transition(page, None, || cart_transition(page, elements, timeouts.cart)).await?
+    {
+        report.check(page, "cart picker", &["semester_cart"]).await;
+    }
+    let cart = enter_cart(page, elements, None, args.term.as_deref(), timeouts).await?;
+    wait::for_selector(page, &elements.course_row, timeouts.courses).await?;
+    report.check_url(page, "cart").await;
+    report
+        .check(
+            page,
+            "cart",
+            &[
+                "course_row",
+                "checkboxes",
+                "availability",
+                "description",
+                "schedule",
+                "room",
+                "instructor",
+                "credits",
+                "seats",
+                "validate_button",
+                "enroll_button",
+            ],
+        )
+        .await;
+
+    // validating the first course is harmless and reaches the results page
+    let pb = get_progress_bar("Validating the first course for its results page...");
+    let validated = async {
+        wait::for_element(page, &elements.checkboxes, timeouts.selection)
+            .await?
+            .click()
+            .await?;
+        wait::for_element(page, &elements.validate_button, timeouts.enroll)
+            .await?
+            .click()
+            .await?;
+        wait::for_selector(page, &elements.results_rows, timeouts.results).await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    }
+    .await;
+    match validated {
+        Ok(_) => {
+            pb.finish_with_message("Reached the results page.");
+            report
+                .check(
+                    page,
+                    "results",
+                    &[
+                        "results_rows",
+                        "result_description",
+                        "result_status",
+                        "registration_success",
+                        "registration_fail",
+                    ],
+                )
+                .await;
+        }
         Err(e) => {
-            if cli_args.debug {
-                page.save_screenshot(
-                    ScreenshotParams::builder().full_page(true).build(),
-                    format!(
-                        "debug-{}.png",
-                        Local::now().format("%H:%M:%S.%3f").to_string()
-                    ),
+            pb.finish_with_message("Couldn't reach the results page.");
+            report.note(format!("Results page not checked: {e}"));
+        }
+    }
+
+    // the confirmation is only looked at, confirming would enroll
+    let pb = get_progress_bar("Opening the enrollment confirmation...");
+    let confirming = async {
+        page.goto(elements.page_url).await?.wait_for_navigation().await?;
+        enter_cart(page, elements, cart.as_deref(), None, timeouts).await?;
+        wait::for_element(page, &elements.checkboxes, timeouts.selection)
+            .await?
+            .click()
+            .await?;
+        wait::for_element(page, &elements.enroll_button, timeouts.enroll)
+            .await?
+            .click()
+            .await?;
+        wait::for_selector(page, &elements.enroll_confirm_button, timeouts.confirm).await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    }
+    .await;
+    match confirming {
+        Ok(_) => pb.finish_with_message("Reached the confirmation, leaving it unanswered."),
+        Err(e) => {
+            pb.finish_with_message("Couldn't reach the confirmation.");
+            report.note(format!("Confirmation only partly checked: {e}"));
+        }
+    }
+    report.check(page, "confirm", &["enroll_confirm_button"]).await;
+    page.goto(elements.page_url).await?.wait_for_navigation().await?;
+
+    println!("{}", report.to_table());
+    for note in report.notes() {
+        println!("{note}");
+    }
+    Ok(())
+}
+
+// The cart and courses an enrollment goes after.
+struct Target {
+    cart: Option<String>,
+    courses: Vec<Course>,
+    ids: Vec<CourseId>,
+}
+
+struct Enrollment {
+    method: Method,
+    time: RegistrationTime,
+}
+
+// Waits for the registration time, keeping the browser alive, then reloads and enrolls.
+async fn enroll(
+    session: &mut Session,
+    elements: &EmoryPageElements,
+    args: &SniperArgs,
+    report: &mut RunReport,
+    credentials: &mut Option<Credentials>,
+    target: &Target,
+    enrollment: Enrollment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeouts = &args.timeouts;
+    let page = &session.page().clone();
+    let registration_time = enrollment.time;
+    let pb = get_progress_bar(format!(
+        "Waiting for registration time: {registration_time}..."
+    ));
+    let registration_hour = registration_time.to_time().hour();
+    report.method = Some(enrollment.method.name().to_string());
+    report.blocking = args.block_resources;
+    let timings = &mut report.timings;
+    // fire at the next matching minute, which is tomorrow once today's passed
+    let now = Local::now();
+    let fire_date = if (registration_hour, registration_time.1) < (now.hour(), now.minute()) {
+        now.date_naive().succ_opt()
+    } else {
+        Some(now.date_naive())
+    };
+    let scheduled = fire_date
+        .and_then(|date| date.and_hms_opt(registration_hour, registration_time.1, 0))
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .ok_or_else(|| format!("{registration_time} doesn't exist in the local timezone"))?;
+    timings.schedule(scheduled);
+    // set up interception before fire time so it costs nothing at reload
+    let mut blocker = match args.block_resources {
+        BlockProfile::None => None,
+        profile => Some(ResourceBlocker::enable(page, profile, elements.status_icons()).await?),
+    };
+    // the wait and reload run with resources blocked; unblock on every way out so a debug
+    // bundle's screenshot and DOM show the page as it really loads
+    let reloaded = async {
+        // latest cookies, restored into a relaunched browser to skip signing in again
+        let mut cookies = session.cookies().await?;
+        let mut failed_recoveries = 0;
+        loop {
+            if !session.is_alive() {
+                pb.set_message("Browser lost, recovering session...");
+                match recover(
+                    session,
+                    elements,
+                    credentials,
+                    target.cart.as_deref(),
+                    &cookies,
+                    timeouts,
                 )
-                .await?;
+                .await
+                {
+                    Ok(()) => {
+                        failed_recoveries = 0;
+                        blocker = match args.block_resources {
+                            BlockProfile::None => None,
+                            profile => Some(
+                                ResourceBlocker::enable(
+                                    session.page(),
+                                    profile,
+                                    elements.status_icons(),
+                                )
+                                .await?,
+                            ),
+                        };
+                        pb.println(format!(
+                            "Browser recovered at {}.",
+                            Local::now().format("%H:%M:%S.%3f")
+                        ));
+                        pb.set_message(format!(
+                            "Waiting for registration time: {registration_time}..."
+                        ));
+                    }
+                    Err(e) => {
+                        failed_recoveries += 1;
+                        if shutdown::is_interrupt(e.as_ref())
+                            || failed_recoveries >= MAX_RECOVERY_ATTEMPTS
+                        {
+                            pb.finish_with_message("Failed to recover the browser.");
+                            return Err(e);
+                        }
+                        pb.println(format!("Recovery failed: {e}"));
+                        sleep(Duration::from_secs(1)).await;
+                    }
+                }
+                continue;
+            }
+            // if registration break
+            let Ok(remaining) = (scheduled - Local::now()).to_std() else {
+                break;
+            };
+            if remaining <= SPIN_WINDOW {
+                // if close stop sleeping, only yielding so Ctrl-C is still noticed
+                async_std::task::yield_now().await;
+            } else {
+                // if far away sleep in steps, checking the browser between them
+                if let Ok(latest) = session.cookies().await {
+                    cookies = latest;
+                }
+                sleep(WAIT_STEP.min(remaining - SPIN_WINDOW)).await;
             }
-            Err(e)?
         }
+        let page = &session.page().clone();
+        pb.finish_with_message(format!(
+            "Reloaded for registration at {}.",
+            timings.mark(Checkpoint::ReloadStart).format("%H:%M:%S.%3f")
+        ));
+
+        page.reload().await?.wait_for_navigation().await?;
+
+        println!(
+            "Page finished loading at {}",
+            timings.mark(Checkpoint::NavigationDone).format("%H:%M:%S.%3f")
+        );
+        Ok::<(), Box<dyn std::error::Error>>(())
     }
+    .await;
+    let unblocked = match blocker.take() {
+        Some(blocker) => blocker.disable().await,
+        None => Ok(()),
+    };
+    reloaded?;
+    unblocked?;
+    // a recovery may have replaced the page
+    let page = &session.page().clone();
+    let registration_results =
+        submit_enrollment(page, elements, &target.ids, enrollment.method, timeouts, timings)
+            .await?;
+    println!("{}", registration_results.to_table());
+    if let (Some(path), IcsScope::Enrolled) = (&args.export_ics, args.ics_scope) {
+        export_ics(path, &ics::enrolled(&target.courses, &registration_results));
+    }
+    report.results = registration_results;
+    report.complete = true;
 
-    // cleanup
-    browser.close().await?;
-    browser.try_wait()?;
-    running.store(false, Ordering::Relaxed);
-    handle.await;
+    // timing report
+    println!("{}", report.timings.to_table());
     Ok(())
 }
 
-async fn run(page: &Page, elements: EmoryPageElements) -> Result<(), Box<dyn std::error::Error>> {
-    // login info
-    let user_name = Text::new("Username: ").prompt()?;
-    let user_pwd = Password::new("Password: ")
-        .with_display_mode(PasswordDisplayMode::Masked)
-        .without_confirmation()
-        .prompt()?;
+// Selects the courses on the loaded cart, enrolls and reads back the results.
+async fn submit_enrollment(
+    page: &Page,
+    elements: &EmoryPageElements,
+    ids: &[CourseId],
+    method: Method,
+    timeouts: &Timeouts,
+    timings: &mut RunTimings,
+) -> Result<Vec<RegistrationResult>, Box<dyn std::error::Error>> {
+    if method == Method::Fast {
+        // Fast method: perform two-step POST directly with current form state
+        println!("FastForm: building selection + sending requests at {}", Local::now().format("%H:%M:%S.%3f"));
+        wait::for_selector(page, &elements.course_row, timeouts.selection).await?;
+        let idxs = elements.locate_courses(page, ids).await?;
+        let sent = fast_form_enroll(page, elements, &idxs).await?;
+        timings.mark_at(Checkpoint::EnrollSent, sent.enroll);
+        timings.mark_at(Checkpoint::ConfirmSent, sent.confirm);
+        println!("FastForm: confirm completed at {}", Local::now().format("%H:%M:%S.%3f"));
+        // Reload to reflect results in DOM before scraping
+        page.reload().await?.wait_for_navigation().await?;
+        println!("FastForm: reloaded to capture results at {}", Local::now().format("%H:%M:%S.%3f"));
+    } else {
+        // Legacy path: select via checkboxes and click through UI
+        let pb = get_progress_bar("Selecting courses...");
+        select_courses(page, elements, ids, timeouts.selection).await?;
+        timings.mark(Checkpoint::SelectionDone);
+        pb.finish_with_message("Courses selected.");
+
+        // enroll button
+        wait::for_element(page, &elements.enroll_button, timeouts.enroll)
+            .await?
+            .click()
+            .await?;
+        println!(
+            "Enroll clicked at {}",
+            timings.mark(Checkpoint::EnrollSent).format("%H:%M:%S.%3f")
+        );
+
+        // confirm
+        wait::for_element(page, &elements.enroll_confirm_button, timeouts.confirm)
+            .await?
+            .click()
+            .await?;
+        println!(
+            "Confirm clicked at {}",
+            timings.mark(Checkpoint::ConfirmSent).format("%H:%M:%S.%3f")
+        );
+    }
+
+    // results
+    let pb = get_progress_bar("Waiting for enrollment results...");
+    wait::for_selector(page, &elements.results_rows, timeouts.results).await?;
+    let registration_results = elements.get_registration_results(page).await?;
+    timings.mark(Checkpoint::ResultsParsed);
+    pb.finish_with_message(format!(
+        "Found {} enrollment results.",
+        registration_results.len()
+    ));
+    Ok(registration_results)
+}
+
+// Reloads the cart until targeted courses have open seats and enrolls in them as they do,
+// until every one of them is enrolled.
+async fn watch(
+    page: &Page,
+    elements: &EmoryPageElements,
+    args: &SniperArgs,
+    watch_args: &WatchArgs,
+    cart: Option<&str>,
+    mut targets: Vec<Course>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let timeouts = &args.timeouts;
+    let pb = get_progress_bar("Watching for open seats...");
+    let mut enrolled_courses: Vec<Course> = Vec::new();
+    loop {
+        let ids: Vec<CourseId> = targets.iter().map(Course::id).collect();
+        let courses = elements.get_cart_courses(page).await?;
+        let open: Vec<Course> = elements::find_courses_by_id(&courses, &ids)?
+            .into_iter()
+            .filter(|course| matches!(course.availability, CourseStatus::Open { .. }))
+            .collect();
+
+        if open.is_empty() {
+            pb.set_message(format!(
+                "No open seats at {}, checking again in {}s...",
+                Local::now().format("%H:%M:%S"),
+                watch_args.interval.as_secs()
+            ));
+            sleep(watch_args.interval).await;
+            page.reload().await?.wait_for_navigation().await?;
+        } else {
+            pb.println(format!(
+                "Seats open in {} at {}",
+                open.iter()
+                    .map(Course::label)
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                Local::now().format("%H:%M:%S.%3f")
+            ));
+            let open_ids: Vec<CourseId> = open.iter().map(Course::id).collect();
+            let results = submit_enrollment(
+                page,
+                elements,
+                &open_ids,
+                watch_args.method,
+                timeouts,
+                &mut RunTimings::default(),
+            )
+            .await?;
+            pb.println(results.to_table().to_string());
+            let enrolled = ics::enrolled(&open, &results);
+            let enrolled_ids: Vec<CourseId> = enrolled.iter().map(|course| course.id()).collect();
+            enrolled_courses.extend(enrolled.into_iter().cloned());
+            targets.retain(|course| !enrolled_ids.contains(&course.id()));
+            if targets.is_empty() {
+                pb.finish_with_message("Enrolled in every watched course.");
+                if let (Some(path), IcsScope::Enrolled) = (&args.export_ics, args.ics_scope) {
+                    export_ics(path, &enrolled_courses.iter().collect::<Vec<&Course>>());
+                }
+                return Ok(());
+            }
+            // back from the results page to the cart
+            page.goto(elements.page_url).await?;
+            enter_cart(page, elements, cart, None, timeouts).await?;
+        }
+        wait::for_selector(page, &elements.course_row, timeouts.courses).await?;
+    }
+}
 
+// Picks the target courses named with `--course` or in the plan, or asks for them.
+fn pick_courses(
+    courses: &[Course],
+    args: &SniperArgs,
+    planned: Option<&[CourseId]>,
+) -> Result<Vec<Course>, Box<dyn std::error::Error>> {
+    let named = if !args.courses.is_empty() {
+        Some(elements::find_courses(courses, &args.courses)?)
+    } else {
+        planned
+            .map(|ids| elements::find_courses_by_id(courses, ids))
+            .transpose()?
+    };
+    if let Some(selected) = named {
+        println!(
+            "Targeting {} ({} credits).",
+            selected
+                .iter()
+                .map(Course::label)
+                .collect::<Vec<String>>()
+                .join(", "),
+            elements::total_credits(&selected)
+        );
+        if let Some(violation) = args.credits.violation(elements::total_credits(&selected)) {
+            match args.credits.credit_check {
+                CreditCheck::Stop => return Err(violation.into()),
+                CreditCheck::Warn => println!("Warning: {violation}."),
+            }
+        }
+        for (course, other) in elements::conflicts(&selected) {
+            println!(
+                "Warning: {} and {} overlap in time.",
+                course.label(),
+                other.label()
+            );
+        }
+        return Ok(selected);
+    }
+
+    // PeopleSoft only reports time conflicts after the enrollment attempt is spent
+    loop {
+        let selected = MultiSelect::new("Select courses:", courses.to_vec())
+            .with_formatter(&|selected| {
+                let total = elements::total_credits(selected.iter().map(|option| option.value));
+                format!(
+                    "{} ({total} credits)",
+                    selected
+                        .iter()
+                        .map(|option| option.value.label())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            })
+            .prompt()?;
+        if let Some(violation) = args.credits.violation(elements::total_credits(&selected)) {
+            match args.credits.credit_check {
+                CreditCheck::Stop => {
+                    println!("{violation}, select again.");
+                    continue;
+                }
+                CreditCheck::Warn => println!("Warning: {violation}."),
+            }
+        }
+        let conflicts = elements::conflicts(&selected);
+        if conflicts.is_empty() {
+            return Ok(selected);
+        }
+        println!("Selected courses overlap in time:");
+        for (course, other) in &conflicts {
+            println!("  {} and {}", course.label(), other.label());
+        }
+        if Confirm::new("Continue with conflicting courses?")
+            .with_default(false)
+            .prompt()?
+        {
+            return Ok(selected);
+        }
+    }
+}
+
+// Writes the `--export-ics` calendar, reporting instead of failing the run on an error.
+fn export_ics(path: &Path, courses: &[&Course]) {
+    match ics::save(path, courses) {
+        Ok(()) => println!(
+            "Calendar with {} courses saved to {}",
+            courses.len(),
+            path.display()
+        ),
+        Err(e) => println!("Failed to save calendar: {e}"),
+    }
+}
+
+// Says why `--export-ics` wrote nothing when the enrolled scope meets a run that doesn't enroll.
+fn skip_enrolled_ics(args: &SniperArgs) {
+    if let (Some(path), IcsScope::Enrolled) = (&args.export_ics, args.ics_scope) {
+        println!(
+            "Nothing exported to {}: --ics-scope enrolled only covers courses this run enrolls in, use --ics-scope cart for the whole cart.",
+            path.display()
+        );
+    }
+}
+
+// Ticks the checkbox of each course in whichever row it is in now.
+async fn select_courses(
+    page: &Page,
+    elements: &EmoryPageElements,
+    ids: &[CourseId],
+    timeout: Duration,
+) -> Result<(), Box<dyn std::error::Error>> {
+    wait::for_elements(page, &elements.checkboxes, timeout).await?;
+    for number in elements.locate_courses(page, ids).await? {
+        page.find_element(format!(r#"[id="DERIVED_REGFRM1_SSR_SELECT${number}"]"#))
+            .await?
+            .click()
+            .await?;
+    }
+    Ok(())
+}
+
+struct Credentials {
+    user_name: String,
+    password: String,
+}
+
+impl Credentials {
+    fn prompt() -> Result<Self, inquire::InquireError> {
+        let user_name = Text::new("Username: ").prompt()?;
+        let password = Password::new("Password: ")
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .without_confirmation()
+            .prompt()?;
+        Ok(Self {
+            user_name,
+            password,
+        })
+    }
+}
+
+// Signs in with the credentials, returns false if the login was rejected.
+async fn login(
+    page: &Page,
+    elements: &EmoryPageElements,
+    credentials: &Credentials,
+    timeouts: &Timeouts,
+) -> Result<bool, Box<dyn std::error::Error>> {
     let pb = get_progress_bar("Logging in with credentials...");
 
     // login
-    page.wait_for_navigation()
-        .await?
-        .find_element(elements.username_input)
+    page.wait_for_navigation().await?;
+    page.find_element(elements.username_input.find(page).await?)
         .await?
         .click()
         .await?
-        .type_str(user_name)
+        .type_str(&credentials.user_name)
         .await?;
-    page.find_element(elements.passwd_input)
+    page.find_element(elements.passwd_input.find(page).await?)
         .await?
         .click()
         .await?
-        .type_str(user_pwd)
+        .type_str(&credentials.password)
         .await?
         .press_key("Enter")
         .await?;
 
     // authentication transition
-    match authentication_transition(&page, &elements, TIMEOUT).await {
+    match transition(page, Some(&pb), || {
+        authentication_transition(page, elements, timeouts.login)
+    })
+    .await
+    {
         Ok(status) => match status {
             AuthTransition::AuthSuccess => pb.finish_with_message("Authenticated."),
             AuthTransition::AuthFail => {
                 pb.finish_with_message("Invalid credentials.");
-                return Ok(());
+                return Ok(false);
             }
             AuthTransition::Duo => {
                 pb.finish_with_message("Duo authentication required.");
                 let pb = get_progress_bar("Waiting for Duo confirmation...");
-                match duo_transition(&page, &elements, TIMEOUT).await {
+                match transition(page, Some(&pb), || duo_transition(page, elements, timeouts.duo))
+                    .await
+                {
                     Ok(status) => match status {
                         DuoTransition::Trust => pb.finish_with_message("Authenticated."),
                         DuoTransition::TimeOut => {
                             pb.finish_with_message("Duo authentication timed out.");
-                            return Ok(());
+                            return Ok(false);
                         }
                         DuoTransition::Cart => pb.finish_with_message("Authenticated."),
                     },
@@ -146,16 +965,38 @@ async fn run(page: &Page, elements: EmoryPageElements) -> Result<(), Box<dyn std
         }
     }
 
-    // pick a shopping cart
+    Ok(true)
+}
+
+// Gets into a shopping cart, picking the cart with the given text or matching the term if
+// there's a choice and prompting otherwise. Returns the text of the cart picked, if any.
+async fn enter_cart(
+    page: &Page,
+    elements: &EmoryPageElements,
+    cart: Option<&str>,
+    term: Option<&str>,
+    timeouts: &Timeouts,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
     let pb = get_progress_bar("Looking for shopping cart...");
-    match cart_transition(&page, &elements, TIMEOUT).await {
+    match transition(page, Some(&pb), || cart_transition(page, elements, timeouts.cart)).await {
         Ok(status) => match status {
-            CartTransition::In => pb.finish_with_message("Entered shopping cart."),
+            CartTransition::In => {
+                pb.finish_with_message("Entered shopping cart.");
+                Ok(None)
+            }
             CartTransition::Select => {
                 pb.finish_with_message("Shopping carts found.");
-                let carts = elements.get_shopping_carts(&page).await?;
-                let selected_cart = Select::new("Select a cart:", carts).prompt()?;
+                let carts = elements.get_shopping_carts(page).await?;
+                let selected_cart = match (cart, term) {
+                    (Some(text), _) => carts
+                        .into_iter()
+                        .find(|cart| cart.text == text)
+                        .ok_or(format!("Shopping cart `{text}` not found"))?,
+                    (None, Some(term)) => elements::find_cart(carts, term)?,
+                    (None, None) => Select::new("Select a cart:", carts).prompt()?,
+                };
                 selected_cart.element.click().await?;
+                Ok(Some(selected_cart.text))
             }
         },
         Err(e) => {
@@ -163,171 +1004,48 @@ async fn run(page: &Page, elements: EmoryPageElements) -> Result<(), Box<dyn std
             Err(e)?
         }
     }
+}
 
-    // get course info
-    let pb = get_progress_bar("Fetching courses in cart...");
-    wait_element_agressive_retry(&page, elements.course_row, TIMEOUT).await?;
-    let courses = elements.get_cart_courses(&page).await?;
-    pb.finish_with_message(format!("Found {} courses.", courses.len()));
-    println!("{}", courses.to_table());
-
-    // pick courses
-    let selected_courses = MultiSelect::new("Select courses:", courses).prompt()?;
-
-    // pick validate or enroll
-    if Select::new("Select action:", vec!["Validate", "Enroll"]).prompt()? == "Enroll" {
-        let method_choice = Select::new(
-            "Choose enrollment method:",
-            vec![
-                "Legacy (click buttons)",
-                "Fast (direct form POST)",
-            ],
-        )
-        .prompt()?;
-        let use_fast = method_choice.starts_with("Fast");
-        //TODO improve registration time selection and implimentation
-        let registration_times: Vec<RegistrationTime> = (1..=12)
-            .flat_map(|hour| {
-                (0..60).flat_map(move |minute| {
-                    [true, false]
-                        .iter()
-                        .map(move |&am| RegistrationTime(hour, minute, am))
-                })
-            })
-            .collect();
-        let registration_time =
-            Select::new("Select registration time:", registration_times).prompt()?;
-        let pb = get_progress_bar(format!(
-            "Waiting for registration time: {registration_time}..."
-        ));
-        let registration_hour = if registration_time.2 {
-            registration_time.0
-        } else {
-            registration_time.0 + 12
+// Brings a crashed or disconnected browser back to the same cart, signing in again when the
+// restored cookies are no longer enough.
+async fn recover(
+    session: &mut Session,
+    elements: &EmoryPageElements,
+    credentials: &mut Option<Credentials>,
+    cart: Option<&str>,
+    cookies: &[Cookie],
+    timeouts: &Timeouts,
+) -> Result<(), Box<dyn std::error::Error>> {
+    session.relaunch(elements, cookies).await?;
+    let page = session.page();
+    let status = transition(page, None, || session_transition(page, elements, timeouts.login)).await?;
+    if let SessionTransition::SignedOut = status {
+        let credentials = match credentials {
+            Some(credentials) => credentials,
+            None => credentials.insert(Credentials::prompt()?),
         };
-        loop {
-            let now = Local::now();
-            // if registration break
-            if now.hour() == registration_hour && now.minute() == registration_time.1 {
-                break;
-            } else if now.hour() == registration_hour
-                && now.minute() == registration_time.1 - 1
-                && now.second() >= 50
-            {
-                // if 10 seconds off stop sleeping
-                continue;
-            } else {
-                // if far away sleep
-                sleep(Duration::from_secs(4)).await;
-            }
-        }
-        pb.finish_with_message(format!(
-            "Reloaded for registration at {}.",
-            Local::now().format("%H:%M:%S.%3f")
-        ));
-
-        page.reload().await?.wait_for_navigation().await?;
-
-        println!(
-            "Page finished loading at {}",
-            Local::now().format("%H:%M:%S.%3f")
-        );
-        if use_fast {
-            // Fast method: perform two-step POST directly with current form state
-            println!("FastForm: building selection + sending requests at {}", Local::now().format("%H:%M:%S.%3f"));
-            let idxs: Vec<u32> = selected_courses.iter().map(|c| c.checkbox_index as u32).collect();
-            fast_form_enroll(&page, &elements, &idxs).await?;
-            println!("FastForm: confirm completed at {}", Local::now().format("%H:%M:%S.%3f"));
-            // Reload to reflect results in DOM before scraping
-            page.reload().await?.wait_for_navigation().await?;
-            println!("FastForm: reloaded to capture results at {}", Local::now().format("%H:%M:%S.%3f"));
-        } else {
-            // Legacy path: select via checkboxes and click through UI
-            let pb = get_progress_bar("Selecting courses...");
-            for (index, checkbox) in wait_elements_agressive_retry(&page, elements.checkboxes, TIMEOUT)
-                .await?
-                .into_iter()
-                .enumerate()
-            {
-                if selected_courses
-                    .iter()
-                    .any(|course| course.checkbox_index == index as u8)
-                {
-                    checkbox.click().await?;
-                }
-            }
-            pb.finish_with_message("Courses selected.");
-
-            // enroll button
-            wait_element_agressive_retry(&page, elements.enroll_button, TIMEOUT)
-                .await?
-                .click()
-                .await?;
-            println!("Enroll clicked at {}", Local::now().format("%H:%M:%S.%3f").to_string());
-
-            // confirm
-            wait_element_agressive_retry(&page, &elements.enroll_confirm_button, TIMEOUT)
-                .await?
-                .click()
-                .await?;
-            println!("Confirm clicked at {}", Local::now().format("%H:%M:%S.%3f").to_string());
-        }
-
-        // results
-        let pb = get_progress_bar("Waiting for enrollment results...");
-        wait_element_agressive_retry(&page, elements.results_rows, TIMEOUT).await?;
-        let registration_results = elements.get_registration_results(&page).await?;
-        pb.finish_with_message(format!(
-            "Found {} enrollment results.",
-            registration_results.len()
-        ));
-        println!("{}", registration_results.to_table());
-    } else {
-        let pb = get_progress_bar("Selecting courses...");
-        for (index, checkbox) in wait_elements_agressive_retry(&page, elements.checkboxes, TIMEOUT)
-            .await?
-            .into_iter()
-            .enumerate()
-        {
-            if selected_courses
-                .iter()
-                .any(|course| course.checkbox_index == index as u8)
-            {
-                checkbox.click().await?;
-            }
+        if !login(page, elements, credentials, timeouts).await? {
+            return Err("Login failed while recovering the browser".into());
         }
-        pb.finish_with_message("Courses selected.");
-
-        // validate
-        wait_element_agressive_retry(&page, elements.validate_button, TIMEOUT)
-            .await?
-            .click()
-            .await?;
-
-        println!(
-            "Validation clicked at {}",
-            Local::now().format("%H:%M:%S.%3f").to_string()
-        );
-        // results
-        let pb = get_progress_bar("Waiting for validation results...");
-        wait_element_agressive_retry(&page, elements.results_rows, TIMEOUT).await?;
-        let registration_results = elements.get_registration_results(&page).await?;
-        pb.finish_with_message(format!(
-            "Found {} validation results.",
-            registration_results.len()
-        ));
-        println!("{}", registration_results.to_table());
     }
-
+    enter_cart(page, elements, cart, None, timeouts).await?;
+    wait::for_selector(page, &elements.course_row, timeouts.courses).await?;
     Ok(())
 }
 
+// Browser-side times at which the fast form sent each POST. Selection is part of the enroll
+// POST, so it has no checkpoint of its own.
+struct FastFormSent {
+    enroll: DateTime<Local>,
+    confirm: DateTime<Local>,
+}
+
 // Performs 2-step POST (Enroll then Confirm) using current form state.
 async fn fast_form_enroll(
     page: &Page,
     _elements: &EmoryPageElements,
-    selected_indexes: &Vec<u32>,
-) -> Result<(), Box<dyn std::error::Error>> {
+    selected_indexes: &[u32],
+) -> Result<FastFormSent, Box<dyn std::error::Error>> {
     let func = r#"
         async function(idxs){
             try{
@@ -343,6 +1061,7 @@ async fn fast_form_enroll(
                 params.set('ICXPos','0');
                 params.set('ICYPos','0');
 
+                const enrollAt = Date.now();
                 const enrollResp = await fetch(postUrl, {method:'POST', headers:{'Content-Type':'application/x-www-form-urlencoded'}, body: params.toString(), credentials:'include'});
                 const enrollText = await enrollResp.text();
                 const m = (enrollText||'').match(/name=['\"]ICStateNum['\"]\s*value=['\"](\d+)/);
@@ -350,9 +1069,10 @@ async fn fast_form_enroll(
                 params.set('ICStateNum', m[1]);
                 params.set('ICAction', '#ICYes');
 
+                const confirmAt = Date.now();
                 const confirmResp = await fetch(postUrl, {method:'POST', headers:{'Content-Type':'application/x-www-form-urlencoded'}, body: params.toString(), credentials:'include'});
                 const confirmText = await confirmResp.text();
-                return {ok:true, bytes: confirmText.length};
+                return {ok:true, bytes: confirmText.length, enrollAt, confirmAt};
             }catch(err){
                 return {ok:false,error:String(err)};
             }
@@ -374,20 +1094,70 @@ async fn fast_form_enroll(
         let err = v.get("error").and_then(|x| x.as_str()).unwrap_or("unknown");
         return Err(format!("FastForm failed: {err}").into());
     }
-    Ok(())
+    let sent_at = |key: &str| {
+        v.get(key)
+            .and_then(|x| x.as_i64())
+            .and_then(|ms| Local.timestamp_millis_opt(ms).single())
+            .unwrap_or_else(Local::now)
+    };
+    Ok(FastFormSent {
+        enroll: sent_at("enrollAt"),
+        confirm: sent_at("confirmAt"),
+    })
+}
+
+// Waits for a transition. When it times out on a page none of its states recognize, pauses
+// (hiding the spinner) so the page can be fixed by hand, then looks again.
+async fn transition<S, F>(
+    page: &Page,
+    pb: Option<&ProgressBar>,
+    detect: impl Fn() -> F,
+) -> Result<S, WaitError>
+where
+    F: Future<Output = Result<S, WaitError>>,
+{
+    loop {
+        match detect().await {
+            Err(e @ WaitError::Timeout { .. }) if pause::is_enabled() => {
+                let stuck = pause::StuckPage::capture(page).await;
+                let resume = match pb {
+                    Some(pb) => pb.suspend(|| stuck.prompt(&e)),
+                    None => stuck.prompt(&e),
+                }
+                .ok_or(WaitError::Cancelled)?;
+                match resume {
+                    pause::Resume::LookAgain => {}
+                    pause::Resume::Reload => {
+                        page.reload().await?;
+                    }
+                    pause::Resume::GiveUp => return Err(e),
+                }
+            }
+            result => return result,
+        }
+    }
 }
 
+#[derive(Clone, Copy)]
 enum CartTransition {
     In,
     Select,
 }
 
+#[derive(Clone, Copy)]
+enum SessionTransition {
+    SignedIn,
+    SignedOut,
+}
+
+#[derive(Clone, Copy)]
 enum AuthTransition {
     AuthSuccess,
     Duo,
     AuthFail,
 }
 
+#[derive(Clone, Copy)]
 enum DuoTransition {
     TimeOut,
     Trust,
@@ -397,181 +1167,82 @@ enum DuoTransition {
 async fn authentication_transition(
     page: &Page,
     elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<AuthTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(elements.login_error).await {
-            Ok(_) => return Ok(AuthTransition::AuthFail),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.duo_waiting).await {
-            Ok(_) => return Ok(AuthTransition::Duo),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(AuthTransition::AuthSuccess),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(AuthTransition::AuthSuccess),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
+    wait_time: Duration,
+) -> Result<AuthTransition, WaitError> {
+    Detector::new(wait_time)
+        .state(&elements.login_error, AuthTransition::AuthFail)
+        .state(&elements.duo_waiting, AuthTransition::Duo)
+        // the Duo prompt is hosted by Duo, catch it before its widget renders
+        .state_when(detect::url_contains("duosecurity.com"), AuthTransition::Duo)
+        .state(&elements.semester_cart, AuthTransition::AuthSuccess)
+        .state(&elements.course_row, AuthTransition::AuthSuccess)
+        .poll(Poll::Mutations(Duration::from_millis(500)))
+        .detect(page)
+        .await
 }
 
 async fn duo_transition(
     page: &Page,
     elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<DuoTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    let mut code_announced = false;
-    loop {
-        if !code_announced {
-            match page.find_element(elements.duo_verification_code).await {
-                Ok(element) => {
-                    if let Some(code_text) = element.inner_text().await? {
-                        let code = code_text.trim();
-                        if !code.is_empty() {
-                            println!("Duo verification code: {}", code);
-                            println!("Enter this code in Duo Mobile to approve the login.");
-                            code_announced = true;
-                        }
-                    }
-                }
-                Err(_) => {}
-            }
-        }
-        match page.find_element(elements.duo_trust_browser).await {
-            Ok(element) => {
-                element.click().await?;
-                return Ok(DuoTransition::Trust);
-            }
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.duo_time_out_try_again).await {
-            Ok(_) => return Ok(DuoTransition::TimeOut),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(DuoTransition::Cart),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(DuoTransition::Cart),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
+    wait_time: Duration,
+) -> Result<DuoTransition, WaitError> {
+    Detector::new(wait_time)
+        .notice(&elements.duo_verification_code, |code| {
+            println!("Duo verification code: {}", code);
+            println!("Enter this code in Duo Mobile to approve the login.");
+        })
+        .state(&elements.duo_trust_browser, DuoTransition::Trust)
+        .on_enter(detect::click(&elements.duo_trust_browser))
+        .state(&elements.duo_time_out_try_again, DuoTransition::TimeOut)
+        .state(&elements.semester_cart, DuoTransition::Cart)
+        .state(&elements.course_row, DuoTransition::Cart)
+        .poll(Poll::Mutations(Duration::from_millis(500)))
+        .detect(page)
+        .await
 }
 
-async fn cart_transition(
+async fn session_transition(
     page: &Page,
     elements: &EmoryPageElements,
-    wait_time: u64,
-) -> Result<CartTransition, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(elements.semester_cart).await {
-            Ok(_) => return Ok(CartTransition::Select),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        match page.find_element(elements.course_row).await {
-            Ok(_) => return Ok(CartTransition::In),
-            Err(e) => {
-                if start.elapsed() >= wait_time {
-                    return Err(e);
-                }
-            }
-        }
-        sleep(Duration::from_millis(100)).await;
-    }
+    wait_time: Duration,
+) -> Result<SessionTransition, WaitError> {
+    Detector::new(wait_time)
+        .state(&elements.semester_cart, SessionTransition::SignedIn)
+        .state(&elements.course_row, SessionTransition::SignedIn)
+        .state(&elements.username_input, SessionTransition::SignedOut)
+        .poll(Poll::Mutations(Duration::from_millis(500)))
+        .detect(page)
+        .await
 }
 
-async fn wait_element_agressive_retry(
+async fn cart_transition(
     page: &Page,
-    selector: &str,
-    wait_time: u64,
-) -> Result<Element, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_element(selector).await {
-            Ok(element) => return Ok(element),
-            Err(e) => {
-                if start.elapsed() < wait_time {
-                    continue;
-                } else {
-                    return Err(e);
-                }
-            }
-        }
-    }
+    elements: &EmoryPageElements,
+    wait_time: Duration,
+) -> Result<CartTransition, WaitError> {
+    Detector::new(wait_time)
+        .state(&elements.semester_cart, CartTransition::Select)
+        .state(&elements.course_row, CartTransition::In)
+        .poll(Poll::Mutations(Duration::from_millis(500)))
+        .detect(page)
+        .await
 }
 
-async fn wait_elements_agressive_retry(
-    page: &Page,
-    selector: &str,
-    wait_time: u64,
-) -> Result<Vec<Element>, CdpError> {
-    let start = Instant::now();
-    let wait_time = Duration::new(wait_time, 0);
-    loop {
-        match page.find_elements(selector).await {
-            Ok(element) => return Ok(element),
-            Err(e) => {
-                if start.elapsed() < wait_time {
-                    continue;
-                } else {
-                    return Err(e);
-                }
-            }
-        }
+struct RegistrationTime(u32, u32, bool);
+
+impl RegistrationTime {
+    fn to_time(&self) -> NaiveTime {
+        let hour = self.0 % 12 + if self.2 { 0 } else { 12 };
+        NaiveTime::from_hms_opt(hour, self.1, 0).unwrap_or_default()
     }
 }
 
-struct RegistrationTime(u32, u32, bool);
+impl From<NaiveTime> for RegistrationTime {
+    fn from(time: NaiveTime) -> Self {
+        let (pm, hour) = time.hour12();
+        RegistrationTime(hour, time.minute(), !pm)
+    }
+}
 
 impl fmt::Display for RegistrationTime {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
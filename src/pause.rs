@@ -0,0 +1,98 @@
+use crate::wait::WaitError;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::Page;
+use chrono::Local;
+use inquire::{InquireError, Select};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static VISIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Lets timed out transitions pause for the page to be fixed by hand. `visible` is whether the
+/// user can see the browser, in a headed window or through `--connect`.
+pub fn enable(visible: bool) {
+    VISIBLE.store(visible, Ordering::SeqCst);
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// What to do once an unrecognized page has been looked at.
+#[derive(Debug, Clone, Copy)]
+pub enum Resume {
+    LookAgain,
+    Reload,
+    GiveUp,
+}
+
+impl fmt::Display for Resume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Resume::LookAgain => write!(f, "Fixed, look again"),
+            Resume::Reload => write!(f, "Reload the page and look again"),
+            Resume::GiveUp => write!(f, "Give up"),
+        }
+    }
+}
+
+/// A page none of a transition's states recognized, e.g. a maintenance banner, a security
+/// question or a password change.
+pub struct StuckPage {
+    url: String,
+    title: String,
+    screenshot: Result<PathBuf, String>,
+}
+
+impl StuckPage {
+    /// Saves a screenshot of the page and notes where it is.
+    pub async fn capture(page: &Page) -> Self {
+        let path = PathBuf::from(format!(
+            "course-sniper-stuck-{}.png",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let screenshot = page
+            .save_screenshot(ScreenshotParams::builder().full_page(true).build(), &path)
+            .await
+            .map(|_| path)
+            .map_err(|e| e.to_string());
+        Self {
+            url: page.url().await.ok().flatten().unwrap_or_default(),
+            title: page.get_title().await.ok().flatten().unwrap_or_default(),
+            screenshot,
+        }
+    }
+
+    /// Shows the page and waits for the user to say how to go on. Returns None on Ctrl-C.
+    pub fn prompt(&self, error: &WaitError) -> Option<Resume> {
+        println!("\nStuck on a page that wasn't recognized: {error}");
+        println!("  URL:        {}", self.url);
+        println!("  Title:      {}", self.title);
+        match &self.screenshot {
+            Ok(path) => println!("  Screenshot: {}", path.display()),
+            Err(e) => println!("  Screenshot: failed ({e})"),
+        }
+        if VISIBLE.load(Ordering::SeqCst) {
+            println!("Fix the page by hand in the browser, then look again.");
+        } else {
+            println!(
+                "The browser is headless, so the screenshot is all there is to see. Look again \
+                 if the page is temporary (e.g. maintenance), or rerun with --attach or --connect \
+                 to fix pages like this by hand."
+            );
+        }
+        match Select::new(
+            "How should the run go on?",
+            vec![Resume::LookAgain, Resume::Reload, Resume::GiveUp],
+        )
+        .prompt()
+        {
+            Ok(resume) => Some(resume),
+            Err(InquireError::OperationInterrupted) => None,
+            Err(_) => Some(Resume::GiveUp),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::elements::CourseId;
+use chrono::NaiveTime;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// What to do with the selected courses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Validate,
+    Enroll,
+    Benchmark,
+}
+
+impl Action {
+    pub fn all() -> Vec<Action> {
+        vec![Action::Validate, Action::Enroll, Action::Benchmark]
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Validate => write!(f, "Validate"),
+            Action::Enroll => write!(f, "Enroll"),
+            Action::Benchmark => write!(f, "Benchmark reload"),
+        }
+    }
+}
+
+/// How the enrollment is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    /// Click the enroll and confirm buttons
+    Legacy,
+    /// Post the enroll and confirm forms directly
+    Fast,
+}
+
+impl Method {
+    pub fn all() -> Vec<Method> {
+        vec![Method::Legacy, Method::Fast]
+    }
+
+    /// Name used in the run report.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Legacy => "legacy",
+            Method::Fast => "fast",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::Legacy => write!(f, "Legacy (click buttons)"),
+            Method::Fast => write!(f, "Fast (direct form POST)"),
+        }
+    }
+}
+
+/// The choices of an interactive run, saved with `--save-plan` and replayed with `--plan`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    /// Text of the shopping cart picked, when there was a choice of carts.
+    pub term: Option<String>,
+    pub courses: Vec<CourseId>,
+    pub action: Action,
+    /// Only set for enrollments.
+    pub method: Option<Method>,
+    /// Local registration time, only set for enrollments.
+    pub time: Option<NaiveTime>,
+    /// Seconds by phase (e.g. `login`) for the timeouts set by a flag or an earlier plan, the
+    /// rest use defaults.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub timeouts: BTreeMap<String, u64>,
+}
+
+impl Plan {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let plan = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read plan {}: {e}", path.display()))?;
+        serde_json::from_str(&plan)
+            .map_err(|e| format!("Invalid plan {}: {e}", path.display()).into())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_plan_loads_back() {
+        let plan = Plan {
+            term: Some("Fall 2025".to_string()),
+            courses: vec![
+                CourseId::ClassNumber(1234),
+                CourseId::Description("PE 101-2 LAB Fitness".to_string()),
+            ],
+            action: Action::Enroll,
+            method: Some(Method::Fast),
+            time: NaiveTime::from_hms_opt(7, 30, 0),
+            timeouts: BTreeMap::from([("login".to_string(), 60)]),
+        };
+        let path = std::env::temp_dir().join(format!("course-sniper-plan-{}.json", std::process::id()));
+        plan.save(&path).unwrap();
+        let loaded = Plan::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), plan);
+    }
+}
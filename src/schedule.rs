@@ -0,0 +1,266 @@
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use serde::Serialize;
+use std::fmt;
+use std::iter::Peekable;
+
+const DAYS: [(&str, Weekday); 7] = [
+    ("Mo", Weekday::Mon),
+    ("Tu", Weekday::Tue),
+    ("We", Weekday::Wed),
+    ("Th", Weekday::Thu),
+    ("Fr", Weekday::Fri),
+    ("Sa", Weekday::Sat),
+    ("Su", Weekday::Sun),
+];
+
+/// One recurring class meeting from the cart's schedule column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Meeting {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// First and last day of the meeting, when the schedule lists them.
+    pub dates: Option<(NaiveDate, NaiveDate)>,
+}
+
+impl Meeting {
+    /// Whether both meetings take place at the same time on some day. Meetings without date
+    /// ranges are taken to run all term.
+    pub fn overlaps(&self, other: &Meeting) -> bool {
+        let same_day = self.days.iter().any(|day| other.days.contains(day));
+        let same_time = self.start < other.end && other.start < self.end;
+        let same_dates = match (self.dates, other.dates) {
+            (Some((first, last)), Some((other_first, other_last))) => {
+                first <= other_last && other_first <= last
+            }
+            _ => true,
+        };
+        same_day && same_time && same_dates
+    }
+}
+
+impl fmt::Display for Meeting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for day in &self.days {
+            if let Some((abbreviation, _)) = DAYS.iter().find(|(_, weekday)| weekday == day) {
+                write!(f, "{abbreviation}")?;
+            }
+        }
+        write!(
+            f,
+            " {} - {}",
+            self.start.format("%-I:%M%p"),
+            self.end.format("%-I:%M%p")
+        )?;
+        if let Some((first, last)) = self.dates {
+            write!(f, " ({} - {})", first.format("%m/%d"), last.format("%m/%d"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses every meeting in a PeopleSoft schedule such as "MoWe 10:00AM - 11:15AM".
+///
+/// Meetings can be on separate lines or run together, and a date range such as
+/// "01/13/2025 - 04/28/2025" applies to the meetings listed before it. Anything that isn't a
+/// meeting, like "TBA" or a room, is skipped, so an unscheduled class has no meetings.
+pub fn parse(schedule: &str) -> Vec<Meeting> {
+    let normalized = schedule.replace('-', " - ");
+    let mut tokens = normalized.split_whitespace().peekable();
+    let mut meetings: Vec<Meeting> = Vec::new();
+    // meetings from here on haven't had a date range yet
+    let mut undated = 0;
+
+    while let Some(token) = tokens.next() {
+        if let Some(days) = parse_days(token) {
+            let Some(start) = take_time(&mut tokens) else {
+                continue;
+            };
+            if !take_separator(&mut tokens) {
+                continue;
+            }
+            let Some(end) = take_time(&mut tokens) else {
+                continue;
+            };
+            meetings.push(Meeting {
+                days,
+                start,
+                end,
+                dates: None,
+            });
+        } else if let Some(first) = parse_date(token) {
+            if !take_separator(&mut tokens) {
+                continue;
+            }
+            let Some(last) = tokens.next().and_then(parse_date) else {
+                continue;
+            };
+            for meeting in &mut meetings[undated..] {
+                meeting.dates = Some((first, last));
+            }
+            undated = meetings.len();
+        }
+    }
+    meetings
+}
+
+fn parse_days(token: &str) -> Option<Vec<Weekday>> {
+    if token.is_empty() || !token.len().is_multiple_of(2) || !token.is_ascii() {
+        return None;
+    }
+    (0..token.len())
+        .step_by(2)
+        .map(|i| {
+            DAYS.iter()
+                .find(|(abbreviation, _)| *abbreviation == &token[i..i + 2])
+                .map(|(_, day)| *day)
+        })
+        .collect()
+}
+
+// Reads a time like "10:00AM", "10:00 AM" or "14:30".
+fn take_time<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Option<NaiveTime> {
+    let time = tokens.next_if(|token| token.contains(':'))?;
+    let suffix = tokens.next_if(|token| {
+        token.eq_ignore_ascii_case("AM") || token.eq_ignore_ascii_case("PM")
+    });
+    let time = match suffix {
+        Some(suffix) => format!("{time}{suffix}"),
+        None => time.to_string(),
+    }
+    .to_uppercase();
+    NaiveTime::parse_from_str(&time, "%I:%M%p")
+        .or_else(|_| NaiveTime::parse_from_str(&time, "%H:%M"))
+        .ok()
+}
+
+fn take_separator<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> bool {
+    tokens
+        .next_if(|token| *token == "-" || token.eq_ignore_ascii_case("to"))
+        .is_some()
+}
+
+fn parse_date(token: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(token, "%m/%d/%Y")
+        .or_else(|_| NaiveDate::parse_from_str(token, "%m/%d/%y"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_single_meeting() {
+        assert_eq!(
+            parse("MoWe 10:00AM - 11:15AM"),
+            vec![Meeting {
+                days: vec![Weekday::Mon, Weekday::Wed],
+                start: time(10, 0),
+                end: time(11, 15),
+                dates: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_afternoon_and_noon_times() {
+        let meetings = parse("TuTh 12:30PM - 1:45PM");
+        assert_eq!(meetings.len(), 1);
+        assert_eq!(meetings[0].days, vec![Weekday::Tue, Weekday::Thu]);
+        assert_eq!(meetings[0].start, time(12, 30));
+        assert_eq!(meetings[0].end, time(13, 45));
+    }
+
+    #[test]
+    fn tba_has_no_meetings() {
+        assert!(parse("TBA").is_empty());
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn parses_meetings_on_separate_lines() {
+        let meetings = parse("MoWeFr 9:00AM - 9:50AM\n  Th 2:30PM - 3:20PM");
+        assert_eq!(meetings.len(), 2);
+        assert_eq!(
+            meetings[0].days,
+            vec![Weekday::Mon, Weekday::Wed, Weekday::Fri]
+        );
+        assert_eq!(meetings[1].days, vec![Weekday::Thu]);
+        assert_eq!(meetings[1].start, time(14, 30));
+    }
+
+    #[test]
+    fn mixes_tba_with_scheduled_meetings() {
+        let meetings = parse("TBA\nFr 1:00PM - 3:45PM");
+        assert_eq!(meetings.len(), 1);
+        assert_eq!(meetings[0].days, vec![Weekday::Fri]);
+    }
+
+    #[test]
+    fn accepts_spaced_suffixes_and_to_separator() {
+        let meetings = parse("Sa 10:00 am to 12:00 pm");
+        assert_eq!(meetings.len(), 1);
+        assert_eq!(meetings[0].start, time(10, 0));
+        assert_eq!(meetings[0].end, time(12, 0));
+    }
+
+    #[test]
+    fn accepts_missing_spaces_around_dash() {
+        let meetings = parse("Mo 6:00PM-8:45PM");
+        assert_eq!(meetings.len(), 1);
+        assert_eq!(meetings[0].end, time(20, 45));
+    }
+
+    #[test]
+    fn applies_date_range_to_preceding_meetings() {
+        let meetings = parse(
+            "MoWe 10:00AM - 11:15AM\n01/13/2025 - 02/28/2025\nTuTh 10:00AM - 11:15AM\n03/10/2025 - 04/28/2025",
+        );
+        assert_eq!(meetings.len(), 2);
+        assert_eq!(
+            meetings[0].dates,
+            Some((date(2025, 1, 13), date(2025, 2, 28)))
+        );
+        assert_eq!(
+            meetings[1].dates,
+            Some((date(2025, 3, 10), date(2025, 4, 28)))
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_day_codes() {
+        assert!(parse("Xy 10:00AM - 11:00AM").is_empty());
+    }
+
+    #[test]
+    fn overlapping_meetings_conflict() {
+        let lecture = &parse("MoWe 10:00AM - 11:15AM")[0];
+        assert!(lecture.overlaps(&parse("We 11:00AM - 11:50AM")[0]));
+        // back to back is fine
+        assert!(!lecture.overlaps(&parse("MoWe 11:15AM - 12:05PM")[0]));
+        assert!(!lecture.overlaps(&parse("TuTh 10:00AM - 11:15AM")[0]));
+    }
+
+    #[test]
+    fn meetings_in_different_date_ranges_dont_conflict() {
+        let first_half = &parse("MoWe 10:00AM - 11:15AM 01/13/2025 - 02/28/2025")[0];
+        let second_half = &parse("MoWe 10:00AM - 11:15AM 03/10/2025 - 04/28/2025")[0];
+        assert!(!first_half.overlaps(second_half));
+        assert!(first_half.overlaps(&parse("Mo 10:30AM - 11:00AM")[0]));
+    }
+
+    #[test]
+    fn displays_in_peoplesoft_format() {
+        let meetings = parse("MoWe 10:00AM - 11:15AM");
+        assert_eq!(meetings[0].to_string(), "MoWe 10:00AM - 11:15AM");
+    }
+}
@@ -0,0 +1,201 @@
+use chromiumoxide::cdp::js_protocol::runtime::{CallArgument, CallFunctionOnParams};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::Page;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Mutex;
+
+/// In-page function that finds the first candidate of a selector with a match. Nodes matched
+/// by text are tagged with a `data-sniper-<name>` attribute so CDP queries can find them
+/// again. Returns the candidate's index and nodes, or null.
+macro_rules! find_candidate_js {
+    () => {
+        r#"function(selector){
+            const tag = "data-sniper-" + selector.name;
+            for(const [index, candidate] of selector.candidates.entries()){
+                let nodes;
+                try{
+                    nodes = Array.from(document.querySelectorAll(candidate.css));
+                }catch(e){
+                    continue;
+                }
+                if(candidate.text !== null){
+                    nodes = nodes.filter(node => (node.innerText || node.value || "").includes(candidate.text));
+                    document.querySelectorAll("[" + tag + "]").forEach(node => {
+                        if(!nodes.includes(node)) node.removeAttribute(tag);
+                    });
+                    nodes.forEach(node => {
+                        if(!node.hasAttribute(tag)) node.setAttribute(tag, "");
+                    });
+                }
+                if(nodes.length) return {index, nodes};
+            }
+            return null;
+        }"#
+    };
+}
+pub(crate) use find_candidate_js;
+
+// Index of the matching candidate of each selector, in a single round trip.
+const MATCH_CANDIDATES: &str = concat!(
+    "function(selectors){ const find = ",
+    find_candidate_js!(),
+    r#";
+        return selectors.map(selector => {
+            const found = find(selector);
+            return found ? found.index : null;
+        });
+    }"#
+);
+
+/// Fallbacks that have been reported, so each is only printed once.
+static USED_FALLBACKS: Mutex<Vec<(&'static str, usize)>> = Mutex::new(Vec::new());
+
+/// One way of finding an element: CSS, optionally narrowed to nodes containing some text.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub css: String,
+    pub text: Option<String>,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.text {
+            Some(text) => write!(f, "{} containing {:?}", self.css, text),
+            None => write!(f, "{}", self.css),
+        }
+    }
+}
+
+/// A page element as an ordered list of candidates, the first one with a match wins. Later
+/// candidates keep a run going when the site changes an id the earlier ones rely on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Selector {
+    pub name: &'static str,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Selector {
+    pub fn new(name: &'static str, css: impl Into<String>) -> Self {
+        Self {
+            name,
+            candidates: Vec::new(),
+        }
+        .or(css)
+    }
+
+    /// Adds a fallback tried when the candidates before it match nothing.
+    pub fn or(mut self, css: impl Into<String>) -> Self {
+        self.candidates.push(Candidate {
+            css: css.into(),
+            text: None,
+        });
+        self
+    }
+
+    /// Adds a fallback that only matches nodes whose text contains `text`.
+    pub fn or_containing(mut self, css: impl Into<String>, text: impl Into<String>) -> Self {
+        self.candidates.push(Candidate {
+            css: css.into(),
+            text: Some(text.into()),
+        });
+        self
+    }
+
+    /// CSS that finds the nodes the candidate at `index` matched, once the page was searched.
+    pub fn css(&self, index: usize) -> String {
+        match &self.candidates[index].text {
+            Some(_) => format!("[data-sniper-{}]", self.name),
+            None => self.candidates[index].css.clone(),
+        }
+    }
+
+    /// Notes that the candidate at `index` matched, printing which one the first time a
+    /// fallback is used so the selector can be updated.
+    pub fn used(&self, index: usize) {
+        if index == 0 {
+            return;
+        }
+        let mut reported = USED_FALLBACKS.lock().unwrap_or_else(|e| e.into_inner());
+        if reported.contains(&(self.name, index)) {
+            return;
+        }
+        reported.push((self.name, index));
+        println!(
+            "`{}` fell back to candidate {} of {}: {} (the first, {}, matched nothing)",
+            self.name,
+            index + 1,
+            self.candidates.len(),
+            self.candidates[index],
+            self.candidates[0]
+        );
+    }
+
+    /// CSS for the first candidate with a match, if any.
+    pub async fn resolve(&self, page: &Page) -> Result<Option<String>, CdpError> {
+        Ok(matching(page, &[self]).await?.remove(0).map(|index| self.css(index)))
+    }
+
+    /// CSS for the first candidate with a match, failing when none has one.
+    pub async fn find(&self, page: &Page) -> Result<String, CdpError> {
+        let [css] = find_all(page, [self]).await?;
+        Ok(css)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.candidates
+                .iter()
+                .map(|candidate| candidate.to_string())
+                .collect::<Vec<String>>()
+                .join(" | ")
+        )
+    }
+}
+
+/// Index of the matching candidate of each selector, reporting fallbacks as they're used.
+pub async fn matching(page: &Page, selectors: &[&Selector]) -> Result<Vec<Option<usize>>, CdpError> {
+    let call = CallFunctionOnParams::builder()
+        .function_declaration(MATCH_CANDIDATES)
+        .argument(
+            CallArgument::builder()
+                .value(serde_json::json!(selectors))
+                .build(),
+        )
+        .build()
+        .map_err(CdpError::msg)?;
+    let indexes: Vec<Option<usize>> = page.evaluate_function(call).await?.into_value()?;
+    for (selector, index) in selectors.iter().zip(&indexes) {
+        if let Some(index) = index {
+            selector.used(*index);
+        }
+    }
+    Ok(indexes)
+}
+
+/// CSS for each selector in a single round trip, failing when one of them matches nothing.
+pub async fn find_all<const N: usize>(
+    page: &Page,
+    selectors: [&Selector; N],
+) -> Result<[String; N], CdpError> {
+    let indexes = matching(page, &selectors).await?;
+    let mut found = Vec::with_capacity(N);
+    for (selector, index) in selectors.iter().zip(indexes) {
+        match index {
+            Some(index) => found.push(selector.css(index)),
+            None => {
+                return Err(CdpError::msg(format!(
+                    "Nothing on the page matches `{}` ({})",
+                    selector.name, selector
+                )))
+            }
+        }
+    }
+    found
+        .try_into()
+        .map_err(|_| CdpError::msg("selector count changed"))
+}
@@ -0,0 +1,53 @@
+use crate::wait::WaitError;
+use async_std::channel::{Receiver, Sender};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Exit code used when the run is stopped by Ctrl-C (128 + SIGINT).
+pub const EXIT_CODE: i32 = 130;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static SIGNAL: OnceLock<(Sender<()>, Receiver<()>)> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Interrupted by user")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+fn signal() -> &'static (Sender<()>, Receiver<()>) {
+    SIGNAL.get_or_init(|| async_std::channel::bounded(1))
+}
+
+/// Installs the Ctrl-C handler. A second Ctrl-C while shutting down exits immediately.
+pub fn install() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_CODE);
+        }
+        // closing the channel wakes every waiter at once
+        signal().0.close();
+    })
+}
+
+/// Resolves once shutdown has been requested.
+pub async fn requested() {
+    let _ = signal().1.recv().await;
+}
+
+/// Whether an error means the user asked to stop, either through the signal handler (which
+/// also cancels waits) or through Ctrl-C inside a prompt (which inquire captures as a key press).
+pub fn is_interrupt(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.is::<Interrupted>()
+        || matches!(error.downcast_ref::<WaitError>(), Some(WaitError::Cancelled))
+        || matches!(
+            error.downcast_ref::<inquire::InquireError>(),
+            Some(inquire::InquireError::OperationInterrupted)
+        )
+}
@@ -0,0 +1,123 @@
+use crate::blocking::BlockProfile;
+use crate::elements::{RegistrationResult, ToTable};
+use chrono::{DateTime, Local, TimeDelta};
+use comfy_table::{Attribute, Cell, CellAlignment, Table};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Checkpoint {
+    ReloadStart,
+    NavigationDone,
+    SelectionDone,
+    EnrollSent,
+    ConfirmSent,
+    ResultsParsed,
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Checkpoint::ReloadStart => write!(f, "Reload start"),
+            Checkpoint::NavigationDone => write!(f, "Navigation done"),
+            Checkpoint::SelectionDone => write!(f, "Selection done"),
+            Checkpoint::EnrollSent => write!(f, "Enroll sent"),
+            Checkpoint::ConfirmSent => write!(f, "Confirm sent"),
+            Checkpoint::ResultsParsed => write!(f, "Results parsed"),
+        }
+    }
+}
+
+/// Wall clock times for each step of an enrollment run, relative to the scheduled fire time.
+#[derive(Debug, Default, Serialize)]
+pub struct RunTimings {
+    pub scheduled: Option<DateTime<Local>>,
+    pub checkpoints: Vec<(Checkpoint, DateTime<Local>)>,
+}
+
+impl RunTimings {
+    pub fn schedule(&mut self, at: DateTime<Local>) {
+        self.scheduled = Some(at);
+    }
+
+    /// Records a checkpoint as happening now and returns the recorded time.
+    pub fn mark(&mut self, checkpoint: Checkpoint) -> DateTime<Local> {
+        self.mark_at(checkpoint, Local::now())
+    }
+
+    pub fn mark_at(&mut self, checkpoint: Checkpoint, at: DateTime<Local>) -> DateTime<Local> {
+        self.checkpoints.push((checkpoint, at));
+        at
+    }
+}
+
+impl ToTable for RunTimings {
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_header(vec![
+            Cell::new("Event").add_attribute(Attribute::Bold),
+            Cell::new("Time"),
+            Cell::new("Δ Scheduled"),
+            Cell::new("Δ Previous"),
+        ]);
+
+        if let Some(scheduled) = self.scheduled {
+            table.add_row(vec![
+                Cell::new("Scheduled fire"),
+                Cell::new(scheduled.format("%H:%M:%S.%3f")),
+                Cell::new("-").set_alignment(CellAlignment::Right),
+                Cell::new("-").set_alignment(CellAlignment::Right),
+            ]);
+        }
+
+        let mut previous = self.scheduled;
+        for (checkpoint, at) in &self.checkpoints {
+            let since = |from: Option<DateTime<Local>>| {
+                from.map(|from| format_delta(*at - from))
+                    .unwrap_or("-".to_string())
+            };
+            table.add_row(vec![
+                Cell::new(checkpoint.to_string()),
+                Cell::new(at.format("%H:%M:%S.%3f")),
+                Cell::new(since(self.scheduled)).set_alignment(CellAlignment::Right),
+                Cell::new(since(previous)).set_alignment(CellAlignment::Right),
+            ]);
+            previous = Some(*at);
+        }
+        table
+    }
+}
+
+fn format_delta(delta: TimeDelta) -> String {
+    format!("{:+.3}s", delta.num_milliseconds() as f64 / 1000.0)
+}
+
+/// Everything worth keeping from an enrollment run, written next to the printed results.
+#[derive(Debug, Default, Serialize)]
+pub struct RunReport {
+    pub method: Option<String>,
+    pub blocking: BlockProfile,
+    pub timings: RunTimings,
+    pub results: Vec<RegistrationResult>,
+    /// False when the run was stopped before results were parsed.
+    pub complete: bool,
+}
+
+impl RunReport {
+    /// Whether an enrollment was started, so there is something worth saving.
+    pub fn is_started(&self) -> bool {
+        self.method.is_some()
+    }
+
+    /// Saves the report as `course-sniper-run-<timestamp>.json` in the working directory.
+    pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = PathBuf::from(format!(
+            "course-sniper-run-{}.json",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,206 @@
+use crate::selector::{find_candidate_js, Selector};
+use crate::shutdown;
+use async_std::task::sleep;
+use chromiumoxide::cdp::js_protocol::runtime::{CallArgument, CallFunctionOnParams};
+use chromiumoxide::error::CdpError;
+use chromiumoxide::{Element, Page};
+use futures::future::{self, Either};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Longest single in-page observation, kept under the CDP request timeout. Also bounds how
+/// long an observer can outlive a cancelled wait.
+const OBSERVE_SLICE: Duration = Duration::from_secs(5);
+/// Back-off when the page has no usable execution context, e.g. mid navigation.
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+// Resolves the index of the first candidate to match as soon as one does, or null once the
+// timeout passes.
+const OBSERVE_SELECTOR: &str = concat!(
+    "function(selector, timeoutMs){ const find = ",
+    find_candidate_js!(),
+    r#";
+        return new Promise(resolve => {
+            const first = find(selector);
+            if(first) return resolve(first.index);
+            const observer = new MutationObserver(() => {
+                const found = find(selector);
+                if(found) done(found.index);
+            });
+            const timer = setTimeout(() => done(null), timeoutMs);
+            function done(index){
+                observer.disconnect();
+                clearTimeout(timer);
+                resolve(index);
+            }
+            observer.observe(document, {childList: true, subtree: true, attributes: true});
+        });
+    }"#
+);
+
+// Resolves true on the next DOM change, or false once the timeout passes.
+const OBSERVE_MUTATION: &str = r#"
+    function(timeoutMs){
+        return new Promise(resolve => {
+            const observer = new MutationObserver(() => done(true));
+            const timer = setTimeout(() => done(false), timeoutMs);
+            function done(changed){
+                observer.disconnect();
+                clearTimeout(timer);
+                resolve(changed);
+            }
+            observer.observe(document, {childList: true, subtree: true, attributes: true});
+        });
+    }
+"#;
+
+#[derive(Debug)]
+pub enum WaitError {
+    Timeout { selector: String, after: Duration },
+    Cancelled,
+    Cdp(CdpError),
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WaitError::Timeout { selector, after } => write!(
+                f,
+                "Timed out after {}s waiting for `{}`",
+                after.as_secs(),
+                selector
+            ),
+            WaitError::Cancelled => write!(f, "Wait cancelled"),
+            WaitError::Cdp(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for WaitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WaitError::Cdp(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<CdpError> for WaitError {
+    fn from(e: CdpError) -> Self {
+        WaitError::Cdp(e)
+    }
+}
+
+/// Waits for the first element matching the selector.
+pub async fn for_element(
+    page: &Page,
+    selector: &Selector,
+    timeout: Duration,
+) -> Result<Element, WaitError> {
+    let css = for_selector(page, selector, timeout).await?;
+    Ok(page.find_element(css).await?)
+}
+
+/// Waits until the selector matches at least one element and returns all of them.
+pub async fn for_elements(
+    page: &Page,
+    selector: &Selector,
+    timeout: Duration,
+) -> Result<Vec<Element>, WaitError> {
+    let css = for_selector(page, selector, timeout).await?;
+    Ok(page.find_elements(css).await?)
+}
+
+/// Waits for one of the selector's candidates to match using a `MutationObserver` injected
+/// into the page, so the wait resolves on the DOM change itself instead of polling over CDP.
+/// Returns the CSS that finds the matched nodes.
+///
+/// Dropping the future or a Ctrl-C cancels the wait.
+pub async fn for_selector(
+    page: &Page,
+    selector: &Selector,
+    timeout: Duration,
+) -> Result<String, WaitError> {
+    match future::select(
+        Box::pin(observe_until(page, selector, timeout)),
+        Box::pin(shutdown::requested()),
+    )
+    .await
+    {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(WaitError::Cancelled),
+    }
+}
+
+async fn observe_until(
+    page: &Page,
+    selector: &Selector,
+    timeout: Duration,
+) -> Result<String, WaitError> {
+    let start = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(WaitError::Timeout {
+                selector: selector.to_string(),
+                after: timeout,
+            });
+        }
+        match observe(page, selector, remaining.min(OBSERVE_SLICE)).await {
+            Ok(Some(index)) => {
+                selector.used(index);
+                return Ok(selector.css(index));
+            }
+            Ok(None) => continue,
+            // a navigation destroyed the context the observer lived in, watch the new document
+            Err(e) => {
+                if start.elapsed() >= timeout {
+                    return Err(e.into());
+                }
+                sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn observe(
+    page: &Page,
+    selector: &Selector,
+    timeout: Duration,
+) -> Result<Option<usize>, CdpError> {
+    let call = CallFunctionOnParams::builder()
+        .function_declaration(OBSERVE_SELECTOR)
+        .argument(
+            CallArgument::builder()
+                .value(serde_json::json!(selector))
+                .build(),
+        )
+        .argument(
+            CallArgument::builder()
+                .value(serde_json::json!(timeout.as_millis() as u64))
+                .build(),
+        )
+        .build()
+        .map_err(CdpError::msg)?;
+    page.evaluate_function(call)
+        .await?
+        .into_value()
+        .map_err(CdpError::from)
+}
+
+/// Waits for the next change to the DOM, or at most `max`. Returns whether anything changed.
+pub async fn for_mutation(page: &Page, max: Duration) -> Result<bool, CdpError> {
+    let call = CallFunctionOnParams::builder()
+        .function_declaration(OBSERVE_MUTATION)
+        .argument(
+            CallArgument::builder()
+                .value(serde_json::json!(max.min(OBSERVE_SLICE).as_millis() as u64))
+                .build(),
+        )
+        .build()
+        .map_err(CdpError::msg)?;
+    page.evaluate_function(call)
+        .await?
+        .into_value()
+        .map_err(CdpError::from)
+}